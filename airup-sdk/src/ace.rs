@@ -0,0 +1,534 @@
+//! Parser of the Airup Command Engine (ACE) language.
+//!
+//! This only parses and expands ACE scripts. They are executed by `airupd`.
+
+peg::parser! {
+    grammar ace() for str {
+        // ==- Commons -==
+        rule _()
+            = quiet!{comment()}
+            / quiet!{[x if x.is_ascii_whitespace()]}
+
+        // ==- Comments -==
+        rule comment()
+            = quiet! {"#" [^ '\n']* "\n"}
+            / expected!("comment")
+
+        // ==- Text Literals -==
+        rule ascii_escape() -> char
+            = "\\" esc:[x if ['n', 'r', 't', '\\', '0'].contains(&x)] { map_ascii_escape(esc) }
+
+        rule quote_escape() -> char
+            = "\\" esc:[x if ['\'', '"', '$'].contains(&x)] { esc }
+
+        rule text_literal_escape() -> char
+            = x:(ascii_escape() / quote_escape()) { x }
+
+        rule strong_string_literal() -> Expr
+            = "\"" s:strong_segment()* "\"" { Expr::new(s) }
+
+        rule strong_segment() -> Segment
+            = variable()
+            / s:(text_literal_escape() / [^ '\n' | '\r' | '\\' | '\"' | '$'])+ { Segment::Literal(s.into_iter().collect()) }
+            / "$" { Segment::Literal("$".into()) }
+
+        rule weak_string_literal() -> Expr
+            = !operator() !redirect_operator() ![';' | '"'] s:weak_segment()+ { Expr::new(s) }
+
+        rule weak_segment() -> Segment
+            = variable()
+            / s:$([^ '\n' | '\r' | '\\' | '\"' | ' ' | ';' | '$']+) { Segment::Literal(s.into()) }
+            / "$" { Segment::Literal("$".into()) }
+
+        rule string_literal() -> Expr
+            = quiet! {x:strong_string_literal() { x }}
+            / quiet! {x:weak_string_literal() { x }}
+            / expected!("string literal")
+
+        // ==- Variables -==
+        rule ident() -> String
+            = s:$([^ x if (x.is_ascii_punctuation() && x != '_' && x != '-') || x.is_whitespace() ]+) { s.into() }
+
+        rule short_ident() -> String
+            = s:$(['a'..='z' | 'A'..='Z' | '_'] ['a'..='z' | 'A'..='Z' | '0'..='9' | '_']*) { s.into() }
+
+        rule variable() -> Segment
+            = "${" name:ident() ":-" x:braced_text() "}" { Segment::Var { name, fallback: Fallback::Default(x) } }
+            / "${" name:ident() ":?" x:braced_text() "}" { Segment::Var { name, fallback: Fallback::Error(x) } }
+            / "${" name:ident() "}" { Segment::Var { name, fallback: Fallback::None } }
+            / "$" name:short_ident() { Segment::Var { name, fallback: Fallback::None } }
+
+        rule braced_text() -> Expr
+            = s:braced_segment()* { Expr::new(s) }
+
+        rule braced_segment() -> Segment
+            = variable()
+            / s:$([^ '}' | '$']+) { Segment::Literal(s.into()) }
+            / "$" { Segment::Literal("$".into()) }
+
+        // ==- Expressions -==
+        rule expr() -> Expr
+            = s:string_literal() { s }
+
+        // ==- Redirections -==
+        rule redirect_operator()
+            = "2>" / "&>" / ">" / "<"
+
+        rule redirect() -> Redirect
+            = "2>&1" { Redirect::StderrToStdout }
+            / "&>>" _* path:expr() { Redirect::Both { path, append: true } }
+            / "&>" _* path:expr() { Redirect::Both { path, append: false } }
+            / "2>>" _* path:expr() { Redirect::Stderr { path, append: true } }
+            / "2>" _* path:expr() { Redirect::Stderr { path, append: false } }
+            / ">>" _* path:expr() { Redirect::Stdout { path, append: true } }
+            / ">" _* path:expr() { Redirect::Stdout { path, append: false } }
+            / "<" _* path:expr() { Redirect::Stdin(path) }
+
+        // ==- Scripts -==
+        rule operator()
+            = ("&&" / "||" / "|") &[x if x.is_ascii_whitespace()]
+
+        rule word() -> Word
+            = r:redirect() { Word::Redirect(r) }
+            / e:expr() { Word::Arg(e) }
+
+        rule simple() -> Simple
+            = words:(word() ++ (_*)) {? Simple::from_words(words) }
+
+        rule pipeline() -> Pipeline
+            = first:simple() rest:(_+ "|" &[x if x.is_ascii_whitespace()] _+ x:simple() { x })* {
+                let mut pipeline = vec![first];
+                pipeline.extend(rest);
+                Pipeline(pipeline)
+            }
+
+        rule list_operator() -> Operator
+            = "&&" &[x if x.is_ascii_whitespace()] { Operator::And }
+            / "||" &[x if x.is_ascii_whitespace()] { Operator::Or }
+            / ";" { Operator::Seq }
+
+        pub rule script() -> Script
+            = _* first:pipeline() rest:(_* op:list_operator() _* x:pipeline() { (op, x) })* _* (";" _*)? {
+                Script { first, rest }
+            }
+    }
+}
+
+fn map_ascii_escape(x: char) -> char {
+    match x {
+        'n' => '\n',
+        'r' => '\r',
+        't' => '\t',
+        '\\' => '\\',
+        '0' => '\0',
+        _ => unreachable!(),
+    }
+}
+
+/// An expression, which is a string that may contain variables to expand.
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Expr(Vec<Segment>);
+impl Expr {
+    /// Creates an expression from segments, merging adjacent literals.
+    fn new(segments: Vec<Segment>) -> Self {
+        let mut result: Vec<Segment> = Vec::with_capacity(segments.len());
+        for segment in segments {
+            match (result.last_mut(), segment) {
+                (Some(Segment::Literal(x)), Segment::Literal(y)) => x.push_str(&y),
+                (_, segment) => result.push(segment),
+            }
+        }
+        Self(result)
+    }
+
+    /// Expands the expression, looking variables up with the given function.
+    ///
+    /// An unset variable expands to an empty string, unless it has a fallback. If a `${VAR:?message}` variable is unset or
+    /// empty, an error message is returned.
+    pub fn expand(&self, lookup: &impl Fn(&str) -> Option<String>) -> Result<String, String> {
+        let mut result = String::new();
+        for segment in &self.0 {
+            match segment {
+                Segment::Literal(x) => result.push_str(x),
+                Segment::Var { name, fallback } => {
+                    let value = lookup(name).filter(|x| !x.is_empty());
+                    match (value, fallback) {
+                        (Some(x), _) => result.push_str(&x),
+                        (None, Fallback::None) => {}
+                        (None, Fallback::Default(x)) => result.push_str(&x.expand(lookup)?),
+                        (None, Fallback::Error(x)) => {
+                            let message = match x.expand(lookup)? {
+                                x if x.is_empty() => "parameter null or not set".into(),
+                                x => x,
+                            };
+                            return Err(format!("{name}: {message}"));
+                        }
+                    }
+                }
+            }
+        }
+        Ok(result)
+    }
+}
+impl From<&str> for Expr {
+    fn from(value: &str) -> Self {
+        Self::new(vec![Segment::Literal(value.into())])
+    }
+}
+
+/// A segment of an [`Expr`].
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Segment {
+    /// Literal text.
+    Literal(String),
+
+    /// A variable, `$VAR` or `${VAR}`, optionally with a fallback.
+    Var { name: String, fallback: Fallback },
+}
+
+/// What a variable expands to if it is unset or empty.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Fallback {
+    /// `${VAR}`: Expands to an empty string.
+    None,
+
+    /// `${VAR:-default}`: Expands to the default value.
+    Default(Expr),
+
+    /// `${VAR:?message}`: Fails with the message.
+    Error(Expr),
+}
+
+/// A command, whose expressions are expanded.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Command {
+    pub module: String,
+    pub args: Vec<String>,
+}
+impl Command {
+    /// Splits the first `N` arguments as parameters of a `sudo`-pattern command with parameters, e.g. `@timeout 5000 cmd`.
+    pub fn wrap_params<const N: usize>(mut self) -> Option<([String; N], Self)> {
+        if self.args.len() <= N {
+            return None;
+        }
+        let params: Vec<_> = self.args.drain(..N).collect();
+        let module = self.args.remove(0);
+        Some((
+            params.try_into().ok()?,
+            Self {
+                module,
+                args: self.args,
+            },
+        ))
+    }
+
+    /// Wraps a `sudo`-pattern command.
+    pub fn wrap<T>(mut self, f: impl FnOnce(Self) -> T) -> Option<T> {
+        if self.args.is_empty() {
+            return None;
+        }
+        let module = self.args.remove(0);
+        Some(f(Self {
+            module,
+            args: self.args,
+        }))
+    }
+}
+
+/// A parsed ACE script, which is a list of pipelines joined by `&&`, `||` or `;`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Script {
+    pub first: Pipeline,
+    pub rest: Vec<(Operator, Pipeline)>,
+}
+impl Script {
+    /// Parses a script.
+    pub fn parse(s: &str) -> Result<Self, ParseError> {
+        let s = format!("{s} ");
+        ace::script(&s).map_err(|err| ParseError {
+            line: err.location.line,
+            column: err.location.column,
+            expected: err.expected.to_string(),
+        })
+    }
+
+    /// Returns an iterator over all simple commands of the script, in order they appear.
+    pub fn commands(&self) -> impl Iterator<Item = &Simple> {
+        std::iter::once(&self.first)
+            .chain(self.rest.iter().map(|(_, x)| x))
+            .flat_map(|x| &x.0)
+    }
+}
+
+/// An error occured when parsing an ACE script.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("line {line}, column {column}: expected {expected}")]
+pub struct ParseError {
+    /// Line number of the error, starting from `1`.
+    pub line: usize,
+
+    /// Column number of the error, starting from `1`.
+    pub column: usize,
+
+    /// Description of what was expected at the position.
+    pub expected: String,
+}
+
+/// An operator that joins two pipelines in a [`Script`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Operator {
+    /// `&&`: The following pipeline is run if the previous one succeeded.
+    And,
+
+    /// `||`: The following pipeline is run if the previous one failed.
+    Or,
+
+    /// `;`: The following pipeline is always run.
+    Seq,
+}
+impl Operator {
+    /// Returns `true` if a pipeline following this operator should run, given whether the previous pipeline succeeded.
+    pub fn should_run(self, success: bool) -> bool {
+        match self {
+            Self::And => success,
+            Self::Or => !success,
+            Self::Seq => true,
+        }
+    }
+}
+
+/// A list of simple commands, whose standard output is piped to standard input of the next one.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Pipeline(pub Vec<Simple>);
+
+/// A command with its redirections.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Simple {
+    pub words: Vec<Expr>,
+    pub redirects: Vec<Redirect>,
+}
+impl Simple {
+    fn from_words(words: Vec<Word>) -> Result<Self, &'static str> {
+        let mut args = Vec::with_capacity(words.len());
+        let mut redirects = Vec::new();
+        for word in words {
+            match word {
+                Word::Arg(x) => args.push(x),
+                Word::Redirect(x) => redirects.push(x),
+            }
+        }
+        if args.is_empty() {
+            return Err("command");
+        }
+
+        Ok(Self {
+            words: args,
+            redirects,
+        })
+    }
+
+    /// Expands words of the command, looking variables up with the given function.
+    pub fn expand(&self, lookup: &impl Fn(&str) -> Option<String>) -> Result<Command, String> {
+        let mut args = self
+            .words
+            .iter()
+            .map(|x| x.expand(lookup))
+            .collect::<Result<Vec<_>, _>>()?;
+        let module = args.remove(0);
+
+        Ok(Command { module, args })
+    }
+}
+
+/// A redirection of a command's standard I/O.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Redirect {
+    /// `< path`
+    Stdin(Expr),
+
+    /// `> path` or `>> path`
+    Stdout { path: Expr, append: bool },
+
+    /// `2> path` or `2>> path`
+    Stderr { path: Expr, append: bool },
+
+    /// `&> path` or `&>> path`
+    Both { path: Expr, append: bool },
+
+    /// `2>&1`
+    StderrToStdout,
+}
+
+#[derive(Debug)]
+enum Word {
+    Arg(Expr),
+    Redirect(Redirect),
+}
+
+#[cfg(test)]
+#[test]
+fn tests() {
+    let lookup = |name: &str| match name {
+        "TEST_ENV" => Some("It works!".into()),
+        "EMPTY" => Some(String::new()),
+        _ => None,
+    };
+    let parse = |s: &str| -> Result<Command, String> {
+        let script = Script::parse(s).map_err(|x| x.to_string())?;
+        script.first.0[0].expand(&lookup)
+    };
+
+    assert_eq!(
+        parse("echo \"Hello, world!\"").unwrap(),
+        Command {
+            module: "echo".into(),
+            args: vec!["Hello, world!".into()],
+        }
+    );
+    assert_eq!(
+        parse("echo   ${TEST_ENV}   ").unwrap(),
+        Command {
+            module: "echo".into(),
+            args: vec!["It works!".into()],
+        }
+    );
+    assert_eq!(
+        parse("echo -n Hello, world!").unwrap(),
+        Command {
+            module: "echo".into(),
+            args: vec!["-n".into(), "Hello,".into(), "world!".into()],
+        }
+    );
+    assert_eq!(
+        parse("/bin/create").unwrap(),
+        Command {
+            module: "/bin/create".into(),
+            args: vec![],
+        }
+    );
+    assert_eq!(
+        parse(
+            "echo \"[$TEST_ENV]\" ${EMPTY:-default} ${UNSET:-$TEST_ENV} a-$UNSET-b \"\\$TEST_ENV costs $5\""
+        )
+        .unwrap(),
+        Command {
+            module: "echo".into(),
+            args: vec![
+                "[It works!]".into(),
+                "default".into(),
+                "It works!".into(),
+                "a--b".into(),
+                "$TEST_ENV costs $5".into(),
+            ],
+        }
+    );
+
+    let simple = |words: &[&str], redirects| Simple {
+        words: words.iter().map(|&x| x.into()).collect(),
+        redirects,
+    };
+    assert_eq!(
+        Script::parse("cat < /etc/hosts | grep -v localhost 2>&1 >> out.txt").unwrap(),
+        Script {
+            first: Pipeline(vec![
+                simple(&["cat"], vec![Redirect::Stdin("/etc/hosts".into())]),
+                simple(
+                    &["grep", "-v", "localhost"],
+                    vec![
+                        Redirect::StderrToStdout,
+                        Redirect::Stdout {
+                            path: "out.txt".into(),
+                            append: true,
+                        },
+                    ]
+                ),
+            ]),
+            rest: vec![],
+        }
+    );
+    assert_eq!(
+        Script::parse("mkdir /run/x && touch /run/x/y || echo failed; true;").unwrap(),
+        Script {
+            first: Pipeline(vec![simple(&["mkdir", "/run/x"], vec![])]),
+            rest: vec![
+                (
+                    Operator::And,
+                    Pipeline(vec![simple(&["touch", "/run/x/y"], vec![])])
+                ),
+                (
+                    Operator::Or,
+                    Pipeline(vec![simple(&["echo", "failed"], vec![])])
+                ),
+                (Operator::Seq, Pipeline(vec![simple(&["true"], vec![])])),
+            ],
+        }
+    );
+    assert_eq!(
+        Script::parse("curl http://localhost/?a=1&b=2 a|b").unwrap(),
+        Script {
+            first: Pipeline(vec![simple(
+                &["curl", "http://localhost/?a=1&b=2", "a|b"],
+                vec![]
+            )]),
+            rest: vec![],
+        }
+    );
+
+    parse("echo \"Hello, world!").unwrap_err();
+    Script::parse("echo |").unwrap_err();
+    Script::parse("echo >").unwrap_err();
+    Script::parse("> out.txt").unwrap_err();
+    assert_eq!(
+        parse("echo ${__ENV_NON_EXISTENT__}").unwrap().args,
+        vec![String::new()]
+    );
+    assert_eq!(
+        parse("echo ${__ENV_NON_EXISTENT__:?is required}").unwrap_err(),
+        "__ENV_NON_EXISTENT__: is required"
+    );
+    let err = Script::parse("echo a &&\ntrue && \"b").unwrap_err();
+    assert_eq!((err.line, err.column), (2, 9));
+}
+
+#[cfg(test)]
+#[test]
+fn modifiers() {
+    let parse = |s: &str| -> Command {
+        let script = Script::parse(s).unwrap();
+        script.first.0[0].expand(&|_| None).unwrap()
+    };
+    let command = |module: &str, args: &[&str]| Command {
+        module: module.into(),
+        args: args.iter().map(|&x| x.into()).collect(),
+    };
+
+    let (params, wrapped) = parse("@timeout 5000 sleep 10").wrap_params().unwrap();
+    assert_eq!(params, ["5000"]);
+    assert_eq!(wrapped, command("sleep", &["10"]));
+
+    let (params, wrapped) = parse("@retry 3 1000 curl http://localhost/")
+        .wrap_params()
+        .unwrap();
+    assert_eq!(params, ["3", "1000"]);
+    assert_eq!(wrapped, command("curl", &["http://localhost/"]));
+
+    // Modifiers compose by wrapping the rest of the command
+    let (params, wrapped) = parse("@user nobody @cwd /tmp @env A=1 id -u")
+        .wrap_params()
+        .unwrap();
+    assert_eq!(params, ["nobody"]);
+    let (params, wrapped) = wrapped.wrap_params().unwrap();
+    assert_eq!(params, ["/tmp"]);
+    let (params, wrapped) = wrapped.wrap_params().unwrap();
+    assert_eq!(params, ["A=1"]);
+    assert_eq!(wrapped, command("id", &["-u"]));
+    let wrapped = parse("@nofail @timeout 1 true")
+        .wrap(std::convert::identity)
+        .unwrap();
+    assert_eq!(wrapped, command("@timeout", &["1", "true"]));
+
+    // A following command is required
+    assert!(parse("@timeout 5000").wrap_params::<1>().is_none());
+    assert!(parse("@retry 3 1000").wrap_params::<2>().is_none());
+    assert!(parse("@nofail").wrap(std::convert::identity).is_none());
+}
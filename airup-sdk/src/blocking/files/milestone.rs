@@ -4,14 +4,14 @@ use std::path::Path;
 
 pub trait MilestoneExt {
     fn read_from<P: AsRef<Path>>(path: P) -> Result<Milestone, ReadError>;
-    fn items(&self) -> Vec<milestone::Item>;
+    fn items(&self) -> Vec<milestone::Entry>;
 }
 impl MilestoneExt for Milestone {
     fn read_from<P: AsRef<Path>>(path: P) -> Result<Self, ReadError> {
         read_from(path.as_ref())
     }
 
-    fn items(&self) -> Vec<milestone::Item> {
+    fn items(&self) -> Vec<milestone::Entry> {
         let mut services = Vec::new();
         let chain = DirChain::new(&self.base_dir);
 
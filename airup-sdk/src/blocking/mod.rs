@@ -5,11 +5,13 @@ pub mod rpc;
 use crate::{
     error::ApiError,
     rpc::{Error as IpcError, Request},
+    system::ExitStatus,
 };
 use rpc::{MessageProtoRecvExt, MessageProtoSendExt};
 use serde::{Serialize, de::DeserializeOwned};
 use std::{
     ops::{Deref, DerefMut},
+    os::fd::BorrowedFd,
     path::Path,
 };
 
@@ -52,6 +54,48 @@ impl Connection {
             .into_result())
     }
 
+    /// Executes a command in the execution environment of the specified service, with the given file descriptors as its
+    /// standard input, output and error, then waits for it to exit.
+    pub fn exec_in_service(
+        &mut self,
+        name: &str,
+        command: &[String],
+        stdio: [BorrowedFd; 3],
+    ) -> Result<Result<ExitStatus, ApiError>, IpcError> {
+        self.invoke_with_stdio(
+            Request::new("system.exec_in_service", (name, command)),
+            stdio,
+        )
+    }
+
+    /// Runs an ACE script in the execution environment of the specified service, with the given file descriptors as its
+    /// standard input, output and error, then waits for it to complete.
+    pub fn run_ace_in_service(
+        &mut self,
+        name: &str,
+        script: &str,
+        stdio: [BorrowedFd; 3],
+    ) -> Result<Result<ExitStatus, ApiError>, IpcError> {
+        self.invoke_with_stdio(
+            Request::new("system.run_ace_in_service", (name, script)),
+            stdio,
+        )
+    }
+
+    /// Sends a request, followed by file descriptors of standard I/O, then receives its response.
+    fn invoke_with_stdio<T: DeserializeOwned>(
+        &mut self,
+        req: Request,
+        stdio: [BorrowedFd; 3],
+    ) -> Result<Result<T, ApiError>, IpcError> {
+        self.underlying.send(&req)?;
+        self.underlying.send_fds(&stdio)?;
+        Ok(self
+            .underlying
+            .recv::<crate::rpc::Response>()?
+            .into_result())
+    }
+
     pub fn into_inner(self) -> rpc::Connection {
         self.underlying
     }
@@ -6,7 +6,10 @@ use serde::{Serialize, de::DeserializeOwned};
 use std::{
     io::{Read, Write},
     ops::{Deref, DerefMut},
-    os::unix::net::UnixStream,
+    os::{
+        fd::{AsFd, BorrowedFd},
+        unix::net::UnixStream,
+    },
     path::Path,
 };
 
@@ -48,6 +51,11 @@ impl Connection {
         self.0.send(&buffer)
     }
 
+    /// Sends the given file descriptors to the peer as `SCM_RIGHTS` ancillary data.
+    pub fn send_fds(&mut self, fds: &[BorrowedFd]) -> Result<(), IpcError> {
+        Ok(crate::util::send_fds(self.0.inner.as_fd(), fds)?)
+    }
+
     /// Returns the underlying message protocol.
     pub fn into_inner(self) -> MessageProto<UnixStream> {
         self.0
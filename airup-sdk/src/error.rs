@@ -100,9 +100,20 @@ pub enum ApiError {
     #[error("the unit conflicts with unit `{name}`")]
     ConflictsWith { name: String },
 
+    /// The operation failed because some assertions of the unit are not met.
+    #[error("assertion failed: {message}")]
+    AssertionFailed { message: String },
+
+    /// The operation failed because some required items of the milestone failed.
+    #[error("required items of milestone `{name}` failed")]
+    MilestoneFailed { name: String },
+
     /// ACE parse error.
-    #[error("ace: parse error")]
-    AceParseError,
+    #[error("ace: parse error: {message}")]
+    AceParseError {
+        #[serde(default)]
+        message: String,
+    },
 
     /// An I/O error occured.
     #[error("I/O error: {message}")]
@@ -1,7 +1,7 @@
 //! # Milestones
 
 use serde::{Deserialize, Serialize};
-use std::{path::PathBuf, str::FromStr};
+use std::{fmt::Display, path::PathBuf, str::FromStr};
 
 /// Represents to an Airup milestone.
 #[derive(Debug, Clone)]
@@ -48,6 +48,38 @@ pub struct Metadata {
 
     #[serde(default)]
     pub kind: Kind,
+
+    /// Whether to stop all services that are not required by the milestone after entering it.
+    #[serde(default)]
+    pub isolate: bool,
+
+    /// What to do if any required item of the milestone failed.
+    #[serde(default)]
+    pub on_failure: OnFailure,
+
+    /// The milestone to enter if `on_failure` is `fallback`, `emergency` by default.
+    pub fallback: Option<String>,
+}
+impl Metadata {
+    /// Returns the milestone to enter if `on_failure` is `fallback`.
+    pub fn fallback(&self) -> &str {
+        self.fallback.as_deref().unwrap_or("emergency")
+    }
+}
+
+/// What to do if any required item of a milestone failed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OnFailure {
+    /// Continue entering the milestone.
+    #[default]
+    Continue,
+
+    /// Stop entering the milestone, then enter the fallback milestone.
+    Fallback,
+
+    /// Stop entering the milestone, then reboot the system.
+    Reboot,
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -64,7 +96,48 @@ pub enum Kind {
     Serial,
 }
 
-#[derive(Debug, Clone)]
+/// An item of a milestone, which is a line in its `*.list.airf` files.
+///
+/// An item is required if its verb is followed by `!`, e.g. `start! sshd`. If a required item failed, the milestone fails
+/// as specified by its `on_failure` setting.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    #[serde(flatten)]
+    pub item: Item,
+
+    #[serde(default)]
+    pub required: bool,
+}
+impl FromStr for Entry {
+    type Err = super::ReadError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(' ') {
+            Some((verb, entity)) if verb.ends_with('!') => Ok(Self {
+                item: format!("{} {entity}", &verb[..verb.len() - 1]).parse()?,
+                required: true,
+            }),
+            _ => Ok(Self {
+                item: s.parse()?,
+                required: false,
+            }),
+        }
+    }
+}
+impl Display for Entry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (verb, entity) = match &self.item {
+            Item::Cache(x) => ("cache", x),
+            Item::Start(x) => ("start", x),
+            Item::Run(x) => ("run", x),
+        };
+        let mark = if self.required { "!" } else { "" };
+        write!(f, "{verb}{mark} {entity}")
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum Item {
     Cache(String),
     Start(String),
@@ -89,3 +162,22 @@ impl FromStr for Item {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Entry, Item};
+
+    #[test]
+    fn parse_entry() {
+        let entry: Entry = "start! sshd".parse().unwrap();
+        assert!(matches!(&entry.item, Item::Start(x) if x == "sshd"));
+        assert!(entry.required);
+        assert_eq!(entry.to_string(), "start! sshd");
+
+        let entry: Entry = "run echo a!".parse().unwrap();
+        assert!(matches!(&entry.item, Item::Run(x) if x == "echo a!"));
+        assert!(!entry.required);
+
+        assert!("stop! sshd".parse::<Entry>().is_err());
+    }
+}
@@ -3,7 +3,11 @@
 
 use super::{Named, ReadError, Validate};
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, path::PathBuf, time::Duration};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 /// An Airup service.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -34,6 +38,15 @@ pub struct Service {
     #[serde(default)]
     pub reslimit: Reslimit,
 
+    #[serde(default)]
+    pub directories: Directories,
+
+    #[serde(default)]
+    pub conditions: Conditions,
+
+    #[serde(default)]
+    pub assertions: Conditions,
+
     #[serde(default)]
     pub event_handlers: HashMap<String, String>,
 }
@@ -47,15 +60,27 @@ impl Validate for Service {
     fn validate(&self) -> Result<(), ReadError> {
         let env_user_conflict =
             self.env.login.is_some() && (self.env.uid.is_some() || self.env.gid.is_some());
+        let dynamic_user_conflict = self.env.dynamic_user
+            && (self.env.login.is_some() || self.env.uid.is_some() || self.env.gid.is_some());
         let oneshot_pid_file =
             self.service.pid_file.is_some() && matches!(self.service.kind, Kind::Oneshot);
         let forking_no_pid_file =
             self.service.pid_file.is_none() && matches!(self.service.kind, Kind::Forking);
         let stdin_log = matches!(self.env.stdin, Stdio::Log);
+        let invalid_signal = |x: &Option<String>| {
+            x.as_deref()
+                .is_some_and(|x| crate::signal::parse(x).is_none())
+        };
 
         if env_user_conflict {
             return Err("fields `env.user` conflicts with either `env.uid` or `env.gid`".into());
         }
+        if dynamic_user_conflict {
+            return Err(
+                "field `env.dynamic_user` conflicts with `env.login`, `env.uid` and `env.gid`"
+                    .into(),
+            );
+        }
         if oneshot_pid_file {
             return Err("field `service.pid_file` must not be set with `kind=\"oneshot\"`".into());
         }
@@ -65,6 +90,71 @@ impl Validate for Service {
         if stdin_log {
             return Err("value of field `env.stdin` cannot be \"log\"".into());
         }
+        let invalid_credential = self
+            .env
+            .credentials
+            .keys()
+            .any(|x| x.is_empty() || x == "." || x == ".." || x.contains('/'));
+        if invalid_credential {
+            return Err(
+                "names of credentials in field `env.credentials` must be valid file names".into(),
+            );
+        }
+        let invalid_directory = DirectoryKind::ALL
+            .iter()
+            .flat_map(|&x| self.directories.get(x))
+            .any(|x| {
+                let path = Path::new(x);
+                x.is_empty()
+                    || x.contains(':')
+                    || !path
+                        .components()
+                        .all(|x| matches!(x, std::path::Component::Normal(_)))
+            });
+        if invalid_directory {
+            return Err(
+                "entries of section `directories` must be relative paths without `..` or `:`"
+                    .into(),
+            );
+        }
+        let watchdog = &self.watchdog;
+        let watchdog_field_missing = match watchdog.kind {
+            Some(WatchdogKind::Tcp) => watchdog.tcp.is_none().then_some("tcp"),
+            Some(WatchdogKind::Unix) => watchdog.unix.is_none().then_some("unix"),
+            Some(WatchdogKind::Http) => watchdog.http.is_none().then_some("http"),
+            Some(WatchdogKind::FileFreshness) => watchdog.file.is_none().then_some("file"),
+            _ => None,
+        };
+        if let Some(field) = watchdog_field_missing {
+            return Err(format!(
+                "field `watchdog.{field}` must be set with the watchdog kind of the service"
+            )
+            .into());
+        }
+        if watchdog
+            .http
+            .as_deref()
+            .is_some_and(|x| !x.starts_with("http://"))
+        {
+            return Err("value of field `watchdog.http` must be an `http://` URL".into());
+        }
+        if watchdog.action == WatchdogAction::Remediate && watchdog.remediate.is_none() {
+            return Err(
+                "field `watchdog.remediate` must be set with `action=\"remediate\"`".into(),
+            );
+        }
+        if watchdog.http_status.0 > watchdog.http_status.1 {
+            return Err("value of field `watchdog.http_status` must be a valid range".into());
+        }
+        if invalid_signal(&self.exec.reload_signal) {
+            return Err("value of field `exec.reload_signal` is not a valid signal".into());
+        }
+        if invalid_signal(&self.exec.stop_signal) {
+            return Err("value of field `exec.stop_signal` is not a valid signal".into());
+        }
+        if invalid_signal(&self.exec.kill_signal) {
+            return Err("value of field `exec.kill_signal` is not a valid signal".into());
+        }
 
         Ok(())
     }
@@ -87,6 +177,18 @@ pub struct Env {
     /// GID to execute for the service.
     pub gid: Option<u32>,
 
+    /// Supplementary groups to execute for the service, each of which is either a group name or a GID.
+    #[serde(default)]
+    pub groups: Vec<Group>,
+
+    /// Runs the service as a transient user, whose UID and GID are allocated from the system's dynamic user range when the
+    /// service starts, and released when it stops.
+    ///
+    /// The service gets a private state directory owned by the user, whose path is exported in the `AIRUP_STATE_DIRECTORY`
+    /// environment variable.
+    #[serde(default)]
+    pub dynamic_user: bool,
+
     /// Determines if environment variables from `airupd` process should be removed or not.
     #[serde(default)]
     pub clear_vars: bool,
@@ -116,6 +218,30 @@ pub struct Env {
     /// By default, the service runs with the same environment variables as `airupd`.
     #[serde(default)]
     pub vars: HashMap<String, toml::Value>,
+
+    /// Files to load environment variables from, in `dotenv` format. They are loaded in order when the service starts, and
+    /// variables set in `vars` take precedence over them.
+    ///
+    /// A file is required to exist, unless its path is prefixed with `-`.
+    #[serde(default)]
+    pub env_files: Vec<String>,
+
+    /// Credentials to pass to the service, mapping names of credentials to paths of files to read them from.
+    ///
+    /// Every time the service starts, credentials are copied to a private directory that is only accessible by the service,
+    /// whose path is exported in the `AIRUP_CREDENTIALS_DIRECTORY` environment variable. The directory is removed when the
+    /// service stops.
+    #[serde(default)]
+    pub credentials: HashMap<String, PathBuf>,
+}
+impl Env {
+    /// Returns an iterator over paths of environment files, paired with a flag indicating whether the file is optional.
+    pub fn env_files(&self) -> impl Iterator<Item = (&Path, bool)> {
+        self.env_files.iter().map(|x| match x.strip_prefix('-') {
+            Some(path) => (Path::new(path), true),
+            None => (Path::new(x), false),
+        })
+    }
 }
 impl Default for Env {
     fn default() -> Self {
@@ -123,6 +249,8 @@ impl Default for Env {
             login: None,
             uid: None,
             gid: None,
+            groups: Vec::default(),
+            dynamic_user: false,
             clear_vars: false,
             stdin: Stdio::Nulldev,
             stdout: Stdio::Log,
@@ -130,10 +258,20 @@ impl Default for Env {
             working_dir: None,
             root_dir: None,
             vars: HashMap::default(),
+            env_files: Vec::default(),
+            credentials: HashMap::default(),
         }
     }
 }
 
+/// Reference to a group, either by name or by GID.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum Group {
+    Id(u32),
+    Name(String),
+}
+
 /// Representation of Standard I/O redirection.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "kebab-case")]
@@ -236,6 +374,9 @@ pub struct Exec {
     /// Command to reload the service
     pub reload: Option<String>,
 
+    /// Signal to send to the main process to reload the service, which is used if `reload` is not set
+    pub reload_signal: Option<String>,
+
     /// Command to be executed before stopping the service
     pub pre_stop: Option<String>,
 
@@ -262,6 +403,20 @@ pub struct Exec {
 
     /// Timeout of reloading the service, in milliseconds
     pub reload_timeout: Option<u32>,
+
+    /// Signal to send to the service to stop it, `SIGTERM` by default
+    pub stop_signal: Option<String>,
+
+    /// Signal to send to the service if it did not stop in the grace period, `SIGKILL` by default. If this is not `SIGKILL`,
+    /// and the service still did not stop in 5 seconds, `SIGKILL` is sent.
+    pub kill_signal: Option<String>,
+
+    /// Processes of the service that the stop signal and kill signal are sent to
+    #[serde(default)]
+    pub kill_mode: KillMode,
+
+    /// Time to wait after sending the stop signal before sending the kill signal, in milliseconds
+    pub stop_grace_period: Option<u32>,
 }
 impl Exec {
     #[inline]
@@ -295,6 +450,50 @@ impl Exec {
             .map(|x| x as u64)
             .map(Duration::from_millis)
     }
+
+    #[inline]
+    pub fn stop_grace_period(&self) -> Option<Duration> {
+        self.stop_grace_period
+            .map(|x| x as u64)
+            .map(Duration::from_millis)
+    }
+
+    #[inline]
+    pub fn reload_signal(&self) -> Option<i32> {
+        self.reload_signal.as_deref().and_then(crate::signal::parse)
+    }
+
+    #[inline]
+    pub fn stop_signal(&self) -> i32 {
+        self.stop_signal
+            .as_deref()
+            .and_then(crate::signal::parse)
+            .unwrap_or(libc::SIGTERM)
+    }
+
+    #[inline]
+    pub fn kill_signal(&self) -> i32 {
+        self.kill_signal
+            .as_deref()
+            .and_then(crate::signal::parse)
+            .unwrap_or(libc::SIGKILL)
+    }
+}
+
+/// Processes of a service that stop and kill signals are sent to.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum KillMode {
+    /// Only the main process of the service is signaled.
+    #[default]
+    Main,
+
+    /// The process group led by the main process of the service is signaled.
+    Group,
+
+    /// All processes in the service's realm are signaled. If the realm is unavailable, the process tree of the main process
+    /// is signaled instead.
+    Realm,
 }
 
 /// Retry conditions of a service.
@@ -324,11 +523,73 @@ pub struct Watchdog {
     /// Also mark the service failed on successful exits (`$? == 0`)
     #[serde(default)]
     pub successful_exit: bool,
+
+    /// Number of consecutive failed health checks before the service is considered unhealthy.
+    #[serde(default = "Watchdog::default_failure_threshold")]
+    pub failure_threshold: u32,
+
+    /// Number of consecutive successful health checks before the degraded state of the service is cleared.
+    #[serde(default = "Watchdog::default_success_threshold")]
+    pub success_threshold: u32,
+
+    /// Action to take when the service is considered unhealthy.
+    #[serde(default)]
+    pub action: WatchdogAction,
+
+    /// Command to execute when the service is considered unhealthy, used by the `remediate` action.
+    pub remediate: Option<String>,
+
+    /// Address to connect to in form of `host:port`, used by the `tcp` watchdog.
+    pub tcp: Option<String>,
+
+    /// Path of the Unix socket to connect to, used by the `unix` watchdog.
+    pub unix: Option<PathBuf>,
+
+    /// URL to send `GET` requests to, used by the `http` watchdog. Only `http://` URLs are supported.
+    pub http: Option<String>,
+
+    /// Inclusive range of HTTP status codes that are considered healthy, used by the `http` watchdog.
+    #[serde(default = "Watchdog::default_http_status")]
+    pub http_status: (u16, u16),
+
+    /// Path of the file whose modification time is checked, used by the `file-freshness` watchdog.
+    pub file: Option<PathBuf>,
+
+    /// Maximum age of the file's last modification, in milliseconds, used by the `file-freshness` watchdog.
+    #[serde(default = "Watchdog::default_file_max_age")]
+    pub file_max_age: u64,
 }
 impl Watchdog {
+    /// Returns number of consecutive failed health checks before the service is considered unhealthy, which is at least 1.
+    pub fn failure_threshold(&self) -> u32 {
+        self.failure_threshold.max(1)
+    }
+
     fn default_interval() -> u32 {
         5000
     }
+
+    /// Returns number of consecutive successful health checks before the degraded state of the service is cleared, which is
+    /// at least 1.
+    pub fn success_threshold(&self) -> u32 {
+        self.success_threshold.max(1)
+    }
+
+    fn default_failure_threshold() -> u32 {
+        1
+    }
+
+    fn default_success_threshold() -> u32 {
+        1
+    }
+
+    fn default_http_status() -> (u16, u16) {
+        (200, 399)
+    }
+
+    fn default_file_max_age() -> u64 {
+        60000
+    }
 }
 
 /// Kind of service watchdog.
@@ -340,6 +601,41 @@ pub enum WatchdogKind {
 
     /// The service regularly notifies the supervisor that it's normally running.
     Notify,
+
+    /// The supervisor polls to connect to a TCP address.
+    Tcp,
+
+    /// The supervisor polls to connect to a Unix socket.
+    Unix,
+
+    /// The supervisor polls to send HTTP `GET` requests and check the response status.
+    Http,
+
+    /// The supervisor polls to check that a file has been modified recently.
+    FileFreshness,
+}
+
+/// Action to take when a service is considered unhealthy by the watchdog.
+///
+/// Except `stop`, every action marks the service degraded until enough consecutive health checks succeed.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum WatchdogAction {
+    /// Stops the service and marks it failed.
+    #[default]
+    Stop,
+
+    /// Restarts the service.
+    Restart,
+
+    /// Executes the `watchdog.remediate` command.
+    Remediate,
+
+    /// Triggers the `service_unhealthy` event, whose payload is name of the service.
+    Event,
+
+    /// Only marks the service degraded.
+    Degraded,
 }
 
 /// Resource limitation.
@@ -352,3 +648,148 @@ pub struct Reslimit {
     /// Max memory usage.
     pub memory: Option<u64>,
 }
+
+/// Conditions that are checked before starting a service.
+///
+/// When used as `[conditions]`, the service is skipped if any of them is not met. When used as `[assertions]`, the service
+/// fails to start instead. Entries of path, kernel command-line and architecture lists can be prefixed with `!` to negate
+/// them.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct Conditions {
+    /// Paths that must exist.
+    #[serde(default)]
+    pub path_exists: Vec<String>,
+
+    /// Paths that must be directories.
+    #[serde(default)]
+    pub path_is_directory: Vec<String>,
+
+    /// Paths that must be regular files which are not empty.
+    #[serde(default)]
+    pub file_not_empty: Vec<String>,
+
+    /// Tokens that the kernel command-line must contain. An entry without `=` also matches tokens in the form of
+    /// `entry=value`.
+    #[serde(default)]
+    pub kernel_cmdline: Vec<String>,
+
+    /// Virtualization environment that the system must be running in.
+    pub virtualization: Option<Virtualization>,
+
+    /// Architectures that the system must be one of, named as Rust's `std::env::consts::ARCH`.
+    #[serde(default)]
+    pub architecture: Vec<String>,
+
+    /// Environment variables that must be set to the given values in the supervisor's environment.
+    #[serde(default)]
+    pub environment: HashMap<String, String>,
+}
+
+/// Kind of virtualization that a condition requires.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Virtualization {
+    /// Running on bare metal.
+    None,
+
+    /// Running in a container.
+    Container,
+
+    /// Running in a virtual machine.
+    Vm,
+
+    /// Running in either a container or a virtual machine.
+    Any,
+}
+
+/// Directories that are managed by the supervisor for the service.
+///
+/// Each entry is a path relative to the base directory of its kind. Managed directories are created when the service starts,
+/// owned by the service's user, and their absolute paths are exported in environment variables, separated by `:`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct Directories {
+    /// Directories under `/run`, which are removed when the service stops.
+    #[serde(default)]
+    pub runtime: Vec<String>,
+
+    /// Directories under `/var/lib`.
+    #[serde(default)]
+    pub state: Vec<String>,
+
+    /// Directories under `/var/cache`.
+    #[serde(default)]
+    pub cache: Vec<String>,
+
+    /// Directories under `/var/log`.
+    #[serde(default)]
+    pub logs: Vec<String>,
+
+    /// Access mode of created directories.
+    #[serde(default = "Directories::default_mode")]
+    pub mode: u32,
+}
+impl Directories {
+    /// Returns entries of the given kind.
+    pub fn get(&self, kind: DirectoryKind) -> &[String] {
+        match kind {
+            DirectoryKind::Runtime => &self.runtime,
+            DirectoryKind::State => &self.state,
+            DirectoryKind::Cache => &self.cache,
+            DirectoryKind::Logs => &self.logs,
+        }
+    }
+
+    /// Returns absolute paths of entries of the given kind.
+    pub fn paths(&self, kind: DirectoryKind) -> impl Iterator<Item = PathBuf> + '_ {
+        self.get(kind).iter().map(move |x| kind.base_dir().join(x))
+    }
+
+    fn default_mode() -> u32 {
+        0o755
+    }
+}
+impl Default for Directories {
+    fn default() -> Self {
+        Self {
+            runtime: Vec::new(),
+            state: Vec::new(),
+            cache: Vec::new(),
+            logs: Vec::new(),
+            mode: Self::default_mode(),
+        }
+    }
+}
+
+/// Kind of a managed directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DirectoryKind {
+    Runtime,
+    State,
+    Cache,
+    Logs,
+}
+impl DirectoryKind {
+    pub const ALL: [Self; 4] = [Self::Runtime, Self::State, Self::Cache, Self::Logs];
+
+    /// Returns the base directory that directories of this kind are placed in.
+    pub fn base_dir(self) -> &'static Path {
+        Path::new(match self {
+            Self::Runtime => "/run",
+            Self::State => "/var/lib",
+            Self::Cache => "/var/cache",
+            Self::Logs => "/var/log",
+        })
+    }
+
+    /// Returns name of the environment variable that directories of this kind are exported in.
+    pub fn env_var(self) -> &'static str {
+        match self {
+            Self::Runtime => "AIRUP_RUNTIME_DIRECTORY",
+            Self::State => "AIRUP_STATE_DIRECTORY",
+            Self::Cache => "AIRUP_CACHE_DIRECTORY",
+            Self::Logs => "AIRUP_LOGS_DIRECTORY",
+        }
+    }
+}
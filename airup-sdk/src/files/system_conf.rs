@@ -1,5 +1,5 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::{collections::HashMap, path::PathBuf};
 
 /// Representation of Airup's system config.
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
@@ -23,6 +23,14 @@ pub struct System {
 
     #[serde(default)]
     pub instance_name: String,
+
+    /// Range of UIDs and GIDs that are allocated to services with dynamic users, inclusive.
+    #[serde(default = "default_dynamic_user_range")]
+    pub dynamic_user_range: (u32, u32),
+
+    /// Directory that private state directories of services with dynamic users are stored in.
+    #[serde(default = "default_dynamic_user_state_dir")]
+    pub dynamic_user_state_dir: PathBuf,
 }
 impl Default for System {
     fn default() -> Self {
@@ -30,6 +38,8 @@ impl Default for System {
             os_name: default_os_name(),
             reboot_timeout: default_reboot_timeout(),
             instance_name: String::new(),
+            dynamic_user_range: default_dynamic_user_range(),
+            dynamic_user_state_dir: default_dynamic_user_state_dir(),
         }
     }
 }
@@ -51,3 +61,11 @@ fn default_os_name() -> String {
 fn default_reboot_timeout() -> u32 {
     1200000
 }
+
+fn default_dynamic_user_range() -> (u32, u32) {
+    (61184, 65519)
+}
+
+fn default_dynamic_user_state_dir() -> PathBuf {
+    "/var/lib/airup/private".into()
+}
@@ -10,6 +10,7 @@ pub mod files;
 pub mod info;
 pub mod prelude;
 pub mod rpc;
+pub mod signal;
 pub mod system;
 
 mod util;
@@ -23,6 +24,9 @@ pub mod blocking;
 #[cfg(feature = "ffi")]
 pub mod ffi;
 
+#[cfg(feature = "ace")]
+pub mod ace;
+
 pub use error::ApiError as Error;
 
 use serde::{Serialize, de::DeserializeOwned};
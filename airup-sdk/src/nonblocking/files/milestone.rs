@@ -4,14 +4,14 @@ use std::{future::Future, path::Path};
 
 pub trait MilestoneExt {
     fn read_from<P: AsRef<Path>>(path: P) -> impl Future<Output = Result<Milestone, ReadError>>;
-    fn items(&self) -> impl Future<Output = Vec<milestone::Item>>;
+    fn items(&self) -> impl Future<Output = Vec<milestone::Entry>>;
 }
 impl MilestoneExt for Milestone {
     async fn read_from<P: AsRef<Path>>(path: P) -> Result<Self, ReadError> {
         read_from(path.as_ref()).await
     }
 
-    async fn items(&self) -> Vec<milestone::Item> {
+    async fn items(&self) -> Vec<milestone::Entry> {
         let mut services = Vec::new();
         let chain = DirChain::new(&self.base_dir);
 
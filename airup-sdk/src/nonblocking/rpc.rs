@@ -7,10 +7,11 @@ use std::{
     future::Future,
     io::Cursor,
     ops::{Deref, DerefMut},
+    os::fd::{AsFd, OwnedFd},
     path::Path,
 };
 use tokio::{
-    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, Interest},
     net::{UnixListener, UnixStream},
 };
 
@@ -52,6 +53,16 @@ impl Connection {
         self.0.send(&buffer.into_inner()).await
     }
 
+    /// Receives at most `max` file descriptors sent by the peer as `SCM_RIGHTS` ancillary data.
+    pub async fn recv_fds(&mut self, max: usize) -> Result<Vec<OwnedFd>, IpcError> {
+        let stream = &self.0.inner;
+        Ok(stream
+            .async_io(Interest::READABLE, || {
+                crate::util::recv_fds(stream.as_fd(), max)
+            })
+            .await?)
+    }
+
     /// Returns the underlying message protocol.
     pub fn into_inner(self) -> MessageProto<UnixStream> {
         self.0
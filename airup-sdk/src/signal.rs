@@ -0,0 +1,69 @@
+//! Conversion between names and numbers of Unix signals.
+
+use libc::c_int;
+
+/// Table of signals that can be referred by name.
+const SIGNALS: &[(&str, c_int)] = &[
+    ("HUP", libc::SIGHUP),
+    ("INT", libc::SIGINT),
+    ("QUIT", libc::SIGQUIT),
+    ("ILL", libc::SIGILL),
+    ("TRAP", libc::SIGTRAP),
+    ("ABRT", libc::SIGABRT),
+    ("BUS", libc::SIGBUS),
+    ("FPE", libc::SIGFPE),
+    ("KILL", libc::SIGKILL),
+    ("USR1", libc::SIGUSR1),
+    ("SEGV", libc::SIGSEGV),
+    ("USR2", libc::SIGUSR2),
+    ("PIPE", libc::SIGPIPE),
+    ("ALRM", libc::SIGALRM),
+    ("TERM", libc::SIGTERM),
+    ("CHLD", libc::SIGCHLD),
+    ("CONT", libc::SIGCONT),
+    ("STOP", libc::SIGSTOP),
+    ("TSTP", libc::SIGTSTP),
+    ("TTIN", libc::SIGTTIN),
+    ("TTOU", libc::SIGTTOU),
+    ("URG", libc::SIGURG),
+    ("XCPU", libc::SIGXCPU),
+    ("XFSZ", libc::SIGXFSZ),
+    ("VTALRM", libc::SIGVTALRM),
+    ("PROF", libc::SIGPROF),
+    ("WINCH", libc::SIGWINCH),
+    ("IO", libc::SIGIO),
+    ("SYS", libc::SIGSYS),
+];
+
+/// Parses a signal from a string.
+///
+/// The string can either be a signal number, or a case-insensitive signal name with or without the `SIG` prefix, e.g.
+/// `15`, `TERM`, `SIGTERM` and `sigterm` are all accepted.
+pub fn parse(s: &str) -> Option<c_int> {
+    if let Ok(signum) = s.parse() {
+        return (signum > 0).then_some(signum);
+    }
+
+    let s = s.to_ascii_uppercase();
+    let name = s.strip_prefix("SIG").unwrap_or(&s);
+    SIGNALS.iter().find(|x| x.0 == name).map(|x| x.1)
+}
+
+/// Returns name of the given signal number, without the `SIG` prefix.
+pub fn name_of(signum: c_int) -> Option<&'static str> {
+    SIGNALS.iter().find(|x| x.1 == signum).map(|x| x.0)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn parse() {
+        assert_eq!(super::parse("15"), Some(libc::SIGTERM));
+        assert_eq!(super::parse("TERM"), Some(libc::SIGTERM));
+        assert_eq!(super::parse("SIGHUP"), Some(libc::SIGHUP));
+        assert_eq!(super::parse("sigint"), Some(libc::SIGINT));
+        assert_eq!(super::parse("0"), None);
+        assert_eq!(super::parse("SIGNOTHING"), None);
+        assert_eq!(super::name_of(libc::SIGKILL), Some("KILL"));
+    }
+}
@@ -1,4 +1,7 @@
-use crate::{Error, files::Service};
+use crate::{
+    Error,
+    files::{Service, milestone},
+};
 use serde::{Deserialize, Serialize};
 
 /// Result of querying a service.
@@ -16,6 +19,9 @@ pub struct QueryService {
     /// Memory usage of the whole service, in bytes.
     pub memory_usage: Option<u64>,
 
+    /// Resource usage statistics of the whole service.
+    pub resource_usage: Option<ResourceUsage>,
+
     /// Class of the currently performing supervisor task of the service.
     pub task_class: Option<String>,
 
@@ -27,6 +33,24 @@ pub struct QueryService {
 
     /// Reason the service is last started.
     pub start_reason: Option<ServiceStartReason>,
+
+    /// Reason the service was skipped at its last start, if any of its conditions was not met.
+    pub skipped: Option<String>,
+
+    /// Status of the service's watchdog, if the watchdog is enabled.
+    pub watchdog: Option<WatchdogStatus>,
+
+    /// Number of times the service has been restarted, either requested, by the watchdog or by retrying.
+    pub restart_count: u64,
+
+    /// Time when the service was last restarted.
+    pub last_restart: Option<i64>,
+
+    /// Exit status of the service's main process when it last exited by itself.
+    pub last_exit: Option<ExitStatus>,
+
+    /// Timestamps recorded during the last start of the service.
+    pub start_timings: Option<StartTimings>,
 }
 impl QueryService {
     pub fn default_of(definition: Service) -> Self {
@@ -35,14 +59,128 @@ impl QueryService {
             status_since: None,
             pid: None,
             memory_usage: None,
+            resource_usage: None,
             task_class: None,
             last_error: None,
             definition,
             start_reason: None,
+            skipped: None,
+            watchdog: None,
+            restart_count: 0,
+            last_restart: None,
+            last_exit: None,
+            start_timings: None,
+        }
+    }
+}
+
+/// Timestamps recorded during a start of a service, in milliseconds.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StartTimings {
+    /// Time when the start was requested.
+    pub requested: i64,
+
+    /// Time when dependencies of the service were satisfied.
+    pub dependencies_satisfied: Option<i64>,
+
+    /// Time when `exec.pre_start` began to execute, if it is set.
+    pub pre_start_began: Option<i64>,
+
+    /// Time when `exec.pre_start` completed, if it is set.
+    pub pre_start_finished: Option<i64>,
+
+    /// Time when the service became active.
+    pub active: Option<i64>,
+}
+impl StartTimings {
+    /// Returns how long `exec.pre_start` took, in milliseconds.
+    pub fn pre_start_duration(&self) -> Option<i64> {
+        Some(self.pre_start_finished? - self.pre_start_began?)
+    }
+
+    /// Returns how long the service took to become active after its dependencies were satisfied, in milliseconds.
+    pub fn activation_duration(&self) -> Option<i64> {
+        Some(self.active? - self.dependencies_satisfied?)
+    }
+}
+
+/// Exit status of a process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExitStatus {
+    /// The process exited with the given code.
+    Exited(i32),
+
+    /// The process was terminated by the given signal.
+    Signaled(i32),
+}
+impl ExitStatus {
+    /// Returns an exit code that a shell would report for this status, which is `128 + signum` for signaled processes.
+    pub fn code(self) -> i32 {
+        match self {
+            Self::Exited(code) => code,
+            Self::Signaled(signum) => 128 + signum,
         }
     }
 }
 
+/// Status of a service's watchdog.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WatchdogStatus {
+    /// Indicates whether the service is degraded, i.e. it was considered unhealthy and has not recovered yet.
+    pub degraded: bool,
+
+    /// Number of consecutive failed health checks.
+    pub consecutive_failures: u32,
+
+    /// Number of consecutive successful health checks.
+    pub consecutive_successes: u32,
+}
+
+/// Resource usage statistics of a service.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResourceUsage {
+    /// CPU time spent in user mode, in microseconds.
+    pub cpu_user: u64,
+
+    /// CPU time spent in kernel mode, in microseconds.
+    pub cpu_system: u64,
+
+    /// Bytes read from the storage layer.
+    pub io_read: u64,
+
+    /// Bytes written to the storage layer.
+    pub io_write: u64,
+
+    /// Number of tasks (threads) running in the service.
+    pub tasks: u64,
+
+    /// Peak memory usage of the service, in bytes.
+    pub memory_peak: Option<u64>,
+}
+
+/// Information about a process belonging to a service.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceProcess {
+    /// ID of the process.
+    pub pid: i64,
+
+    /// ID of the process' parent.
+    pub ppid: i64,
+
+    /// Real UID of the process.
+    pub uid: u32,
+
+    /// Name of the process' executable.
+    pub name: String,
+
+    /// Command-line arguments of the process.
+    pub cmdline: Vec<String>,
+
+    /// Timestamp generated when the process was started, in milliseconds.
+    pub start_time: i64,
+}
+
 /// Reason a service is started.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ServiceStartReason {
@@ -56,6 +194,58 @@ pub enum ServiceStartReason {
     Milestone(String),
 }
 
+/// Mode of restarting a service.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RestartMode {
+    /// Stops the service, then starts it. This fails if the service is not active.
+    #[default]
+    Restart,
+
+    /// Restarts the service if it is active, otherwise starts it.
+    TryRestart,
+
+    /// Reloads the service if it is active and supports reloading, otherwise behaves like [`RestartMode::TryRestart`].
+    ReloadOrRestart,
+}
+
+/// A job, which is a queued or running request to operate a service.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Job {
+    /// ID of the job.
+    pub id: u64,
+
+    /// Name of the service that the job operates.
+    pub service: String,
+
+    /// Kind of the job.
+    pub kind: JobKind,
+
+    /// State of the job.
+    pub state: JobState,
+}
+
+/// Kind of a job.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum JobKind {
+    Start,
+    Stop,
+    Reload,
+    Restart(RestartMode),
+}
+
+/// State of a job.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum JobState {
+    /// The job is waiting for other tasks of the service to complete.
+    Waiting,
+
+    /// The job is running.
+    Running,
+}
+
 /// Result of querying information about the whole system.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QuerySystem {
@@ -79,6 +269,27 @@ pub struct QuerySystem {
 
     /// List of cached services in the system.
     pub services: Vec<String>,
+
+    /// Indicates whether any item failed when entering the boot milestone. This is only set once booting completed.
+    #[serde(default)]
+    pub is_degraded: bool,
+
+    /// List of items that failed when entering the boot milestone and milestones entered during booting.
+    #[serde(default)]
+    pub failed_items: Vec<FailedItem>,
+}
+
+/// An item of a milestone that failed when entering the milestone.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailedItem {
+    /// Name of the milestone.
+    pub milestone: String,
+
+    /// The failed item.
+    pub item: milestone::Entry,
+
+    /// Message of the error.
+    pub error: String,
 }
 
 /// Representation of the status of a service.
@@ -119,6 +330,25 @@ pub struct EnteredMilestone {
     pub finish_timestamp: i64,
 }
 
+/// Result of querying information about a milestone.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryMilestone {
+    /// Name of the milestone.
+    pub name: String,
+
+    /// Manifest of the milestone.
+    pub manifest: milestone::Manifest,
+
+    /// Items of the milestone, in order they are executed.
+    pub items: Vec<milestone::Entry>,
+
+    /// Information of the milestone's latest entry in the milestone stack, if it is entered.
+    pub entered: Option<EnteredMilestone>,
+
+    /// List of cached services that were last started to reach the milestone.
+    pub services: Vec<String>,
+}
+
 /// Representation of an Airup event.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Event {
@@ -177,6 +407,48 @@ pub trait ConnectionExt<'a>: crate::Connection {
         self.invoke("system.query_service", name)
     }
 
+    /// Lists processes belonging to the specified service.
+    fn list_service_processes(
+        &'a mut self,
+        name: &'a str,
+    ) -> Self::Invoke<'a, Vec<ServiceProcess>> {
+        self.invoke("system.list_service_processes", name)
+    }
+
+    /// Sends a signal to the specified service. If `all` is `true`, the signal is sent to all processes belonging to the
+    /// service, otherwise it is only sent to the main process.
+    fn signal_service(&'a mut self, name: &'a str, signum: i32, all: bool) -> Self::Invoke<'a, ()> {
+        self.invoke("system.signal_service", (name, signum, all))
+    }
+
+    /// Restarts the specified service in the given mode.
+    fn restart_service(&'a mut self, name: &'a str, mode: RestartMode) -> Self::Invoke<'a, ()> {
+        self.invoke("system.restart_service", (name, mode))
+    }
+
+    /// Submits a job for the specified service without waiting for it to complete, returning ID of the job.
+    ///
+    /// If the service is busy, the job is queued. A job may be merged with a queued one, in which case ID of the merged job is
+    /// returned.
+    fn submit_job(&'a mut self, name: &'a str, kind: JobKind) -> Self::Invoke<'a, u64> {
+        self.invoke("system.submit_job", (name, kind))
+    }
+
+    /// Lists all jobs that are not finished yet.
+    fn list_jobs(&'a mut self) -> Self::Invoke<'a, Vec<Job>> {
+        self.invoke("system.list_jobs", ())
+    }
+
+    /// Waits for the specified job to complete.
+    fn wait_job(&'a mut self, id: u64) -> Self::Invoke<'a, ()> {
+        self.invoke("system.wait_job", id)
+    }
+
+    /// Cancels the specified job. If the job is running, its task is interrupted.
+    fn cancel_job(&'a mut self, id: u64) -> Self::Invoke<'a, ()> {
+        self.invoke("system.cancel_job", id)
+    }
+
     /// Interrupts current task running in specific service's supervisor.
     fn interrupt_service_task(&'a mut self, name: &'a str) -> Self::Invoke<'a, ()> {
         self.invoke("system.interrupt_service_task", name)
@@ -202,11 +474,26 @@ pub trait ConnectionExt<'a>: crate::Connection {
         self.invoke("system.gc", ())
     }
 
+    /// Lists names of all milestones installed in the system.
+    fn list_milestones(&'a mut self) -> Self::Invoke<'a, Vec<String>> {
+        self.invoke("system.list_milestones", ())
+    }
+
+    /// Queries information about the specific milestone.
+    fn query_milestone(&'a mut self, name: &'a str) -> Self::Invoke<'a, QueryMilestone> {
+        self.invoke("system.query_milestone", name)
+    }
+
     /// Enters the specific milestone.
     fn enter_milestone(&'a mut self, name: &'a str) -> Self::Invoke<'a, ()> {
         self.invoke("system.enter_milestone", name)
     }
 
+    /// Enters the specific milestone, then stops all services that are not required by it.
+    fn isolate_milestone(&'a mut self, name: &'a str) -> Self::Invoke<'a, ()> {
+        self.invoke("system.isolate_milestone", name)
+    }
+
     /// Sets the server's instance name.
     fn set_instance_name(&'a mut self, name: &'a str) -> Self::Invoke<'a, ()> {
         self.invoke("system.set_instance_name", name)
@@ -221,5 +508,15 @@ pub trait ConnectionExt<'a>: crate::Connection {
     fn unregister_extension(&'a mut self, name: &'a str) -> Self::Invoke<'a, ()> {
         self.invoke("system.unregister_extension", name)
     }
+
+    /// Expands an ACE script in the execution environment of the specified service, without running it. Returns argument
+    /// vectors of all commands in the script, in order they appear.
+    fn expand_ace(
+        &'a mut self,
+        name: &'a str,
+        script: &'a str,
+    ) -> Self::Invoke<'a, Vec<Vec<String>>> {
+        self.invoke("system.expand_ace", (name, script))
+    }
 }
 impl<T> ConnectionExt<'_> for T where T: crate::Connection {}
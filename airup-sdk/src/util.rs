@@ -18,3 +18,119 @@ where
         result
     }
 }
+
+/// Sends a single zero byte to the socket, with the given file descriptors attached as `SCM_RIGHTS` ancillary data.
+#[cfg(feature = "blocking")]
+pub(crate) fn send_fds(
+    socket: std::os::fd::BorrowedFd,
+    fds: &[std::os::fd::BorrowedFd],
+) -> std::io::Result<()> {
+    use std::os::fd::{AsRawFd, RawFd};
+
+    let raw: Vec<RawFd> = fds.iter().map(|x| x.as_raw_fd()).collect();
+    let payload_len = std::mem::size_of_val(&raw[..]) as libc::c_uint;
+    let space = unsafe { libc::CMSG_SPACE(payload_len) } as usize;
+    let mut control = vec![0u64; space.div_ceil(8)];
+    let mut byte = [0u8];
+    let mut iov = libc::iovec {
+        iov_base: byte.as_mut_ptr().cast(),
+        iov_len: 1,
+    };
+
+    // SAFETY: The message header is zero-initialized before the fields we need are filled, and the control buffer is aligned
+    // and large enough to hold a control message with the payload.
+    unsafe {
+        let mut msg: libc::msghdr = std::mem::zeroed();
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        msg.msg_control = control.as_mut_ptr().cast();
+        msg.msg_controllen = space as _;
+
+        let cmsg = libc::CMSG_FIRSTHDR(&msg);
+        (*cmsg).cmsg_level = libc::SOL_SOCKET;
+        (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+        (*cmsg).cmsg_len = libc::CMSG_LEN(payload_len) as _;
+        std::ptr::copy_nonoverlapping(raw.as_ptr(), libc::CMSG_DATA(cmsg).cast(), raw.len());
+
+        if libc::sendmsg(socket.as_raw_fd(), &msg, 0) < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+
+    Ok(())
+}
+
+/// Receives a single byte from the socket, returning at most `max` file descriptors attached to it as `SCM_RIGHTS` ancillary
+/// data. Received file descriptors are marked close-on-exec.
+#[cfg(feature = "nonblocking")]
+pub(crate) fn recv_fds(
+    socket: std::os::fd::BorrowedFd,
+    max: usize,
+) -> std::io::Result<Vec<std::os::fd::OwnedFd>> {
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+
+    #[cfg(any(target_os = "linux", target_os = "android", target_os = "freebsd"))]
+    const FLAGS: libc::c_int = libc::MSG_CMSG_CLOEXEC;
+    #[cfg(not(any(target_os = "linux", target_os = "android", target_os = "freebsd")))]
+    const FLAGS: libc::c_int = 0;
+
+    let payload_len = (max * std::mem::size_of::<RawFd>()) as libc::c_uint;
+    let space = unsafe { libc::CMSG_SPACE(payload_len) } as usize;
+    let mut control = vec![0u64; space.div_ceil(8)];
+    let mut byte = [0u8];
+    let mut iov = libc::iovec {
+        iov_base: byte.as_mut_ptr().cast(),
+        iov_len: 1,
+    };
+    let mut fds = Vec::with_capacity(max);
+
+    // SAFETY: The message header is zero-initialized before the fields we need are filled, and control messages are only
+    // read within the length reported by the kernel.
+    unsafe {
+        let mut msg: libc::msghdr = std::mem::zeroed();
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        msg.msg_control = control.as_mut_ptr().cast();
+        msg.msg_controllen = space as _;
+
+        match libc::recvmsg(socket.as_raw_fd(), &mut msg, FLAGS) {
+            x if x < 0 => return Err(std::io::Error::last_os_error()),
+            0 => return Err(std::io::ErrorKind::UnexpectedEof.into()),
+            _ => {}
+        }
+
+        let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+        while !cmsg.is_null() {
+            if (*cmsg).cmsg_level == libc::SOL_SOCKET && (*cmsg).cmsg_type == libc::SCM_RIGHTS {
+                let len = (*cmsg).cmsg_len as usize - libc::CMSG_LEN(0) as usize;
+                let data = libc::CMSG_DATA(cmsg).cast::<RawFd>();
+                for i in 0..len / std::mem::size_of::<RawFd>() {
+                    let fd = OwnedFd::from_raw_fd(data.add(i).read_unaligned());
+                    if FLAGS == 0 {
+                        libc::fcntl(fd.as_raw_fd(), libc::F_SETFD, libc::FD_CLOEXEC);
+                    }
+                    fds.push(fd);
+                }
+            }
+            cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+        }
+    }
+
+    Ok(fds)
+}
+
+#[cfg(all(test, feature = "blocking", feature = "nonblocking"))]
+mod tests {
+    use std::os::{fd::AsFd, unix::net::UnixStream};
+
+    #[test]
+    fn pass_fds() {
+        let (a, b) = UnixStream::pair().unwrap();
+        let (x, _y) = UnixStream::pair().unwrap();
+        super::send_fds(a.as_fd(), &[x.as_fd(), x.as_fd()]).unwrap();
+        assert_eq!(super::recv_fds(b.as_fd(), 3).unwrap().len(), 2);
+
+        drop(a);
+        super::recv_fds(b.as_fd(), 3).unwrap_err();
+    }
+}
@@ -0,0 +1,237 @@
+use airup_sdk::{
+    ace::{ParseError, Script},
+    blocking::{
+        files::{self, MilestoneExt as _},
+        fs::DirChain,
+    },
+    files::{
+        Milestone, Service,
+        milestone::{Entry, Item},
+    },
+    system::ConnectionExt as _,
+};
+use anyhow::{anyhow, bail};
+use clap::{Parser, Subcommand};
+use console::style;
+use std::os::fd::AsFd;
+
+/// Check or run ACE commands
+#[derive(Debug, Clone, Parser)]
+#[command(about)]
+pub struct Cmdline {
+    #[command(subcommand)]
+    action: Action,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+enum Action {
+    /// Parse ACE commands of services and milestones on disk, without running them
+    Check {
+        /// Services or milestones (with `.airm` suffix) to check, all of them by default
+        units: Vec<String>,
+    },
+
+    /// Run an ACE command in a service's execution environment
+    Run {
+        service: String,
+
+        /// The ACE command to run
+        script: String,
+
+        /// Print the expanded commands without running them
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+pub fn main(cmdline: Cmdline) -> anyhow::Result<()> {
+    match cmdline.action {
+        Action::Check { units } => check(units),
+        Action::Run {
+            service,
+            script,
+            dry_run,
+        } => run(&service, &script, dry_run),
+    }
+}
+
+fn check(mut units: Vec<String>) -> anyhow::Result<()> {
+    if units.is_empty() {
+        units = all_units()?;
+    }
+
+    let mut errors = 0;
+    for unit in &units {
+        let result = match unit.strip_suffix(".airm") {
+            Some(name) => check_milestone(name),
+            None => check_service(unit.strip_suffix(".airs").unwrap_or(unit)),
+        };
+        match result {
+            Ok(problems) => {
+                for problem in &problems {
+                    eprintln!("{} {problem}", style("error:").red().bold());
+                }
+                errors += problems.len();
+            }
+            Err(err) => {
+                eprintln!("{} {unit}: {err}", style("error:").red().bold());
+                errors += 1;
+            }
+        }
+    }
+
+    match errors {
+        0 => Ok(()),
+        1 => bail!("found 1 error"),
+        n => bail!("found {n} errors"),
+    }
+}
+
+/// Returns names of all services and milestones on disk.
+fn all_units() -> anyhow::Result<Vec<String>> {
+    let manifest = airup_sdk::build::manifest();
+    let mut units = Vec::new();
+    for (dir, suffix) in [
+        (&manifest.service_dir, ".airs"),
+        (&manifest.milestone_dir, ".airm"),
+    ] {
+        let names = DirChain::new(dir)
+            .read_chain()
+            .map_err(|e| anyhow!("failed to read `{}`: {}", dir.display(), e))?;
+        units.extend(
+            names
+                .into_iter()
+                .map(|x| x.to_string_lossy().into_owned())
+                .filter(|x| x.ends_with(suffix)),
+        );
+    }
+
+    Ok(units)
+}
+
+/// Parses ACE commands of a service, returning the errors found.
+fn check_service(name: &str) -> anyhow::Result<Vec<String>> {
+    let service: Service = files::read_merge(super::cat::sources_of(name)?)?;
+    let exec = &service.exec;
+
+    let mut problems = Vec::new();
+    let mut check = |field: &str, script: &str, line: usize| {
+        if let Err(err) = parse(script, line, 0) {
+            problems.push(format!("{name}.airs: {field}: {err}"));
+        }
+    };
+    let whole = [
+        ("exec.start", Some(&exec.start)),
+        ("exec.reload", exec.reload.as_ref()),
+        ("exec.stop", exec.stop.as_ref()),
+        ("exec.health-check", exec.health_check.as_ref()),
+    ];
+    for (field, script) in whole {
+        if let Some(x) = script {
+            check(field, x, 0);
+        }
+    }
+
+    // These are executed line by line
+    let lines = [
+        ("exec.pre-start", &exec.pre_start),
+        ("exec.post-start", &exec.post_start),
+        ("exec.pre-stop", &exec.pre_stop),
+        ("exec.post-stop", &exec.post_stop),
+    ];
+    for (field, script) in lines {
+        for (index, line) in script.iter().flat_map(|x| x.lines()).enumerate() {
+            check(field, line, index);
+        }
+    }
+
+    let mut handlers: Vec<_> = service.event_handlers.iter().collect();
+    handlers.sort();
+    for (event, script) in handlers {
+        check(&format!("event_handlers.{event}"), script, 0);
+    }
+
+    Ok(problems)
+}
+
+/// Parses `run` items of a milestone, returning the errors found.
+fn check_milestone(name: &str) -> anyhow::Result<Vec<String>> {
+    let path = DirChain::new(&airup_sdk::build::manifest().milestone_dir)
+        .find(format!("{name}.airm"))
+        .ok_or_else(|| anyhow!("milestone `{name}` was not found on disk"))?;
+    let milestone = Milestone::read_from(&path)?;
+
+    let mut problems = Vec::new();
+    let chain = DirChain::new(&milestone.base_dir);
+    for list in chain.read_chain()? {
+        let list = list.to_string_lossy();
+        if !list.ends_with(".list.airf") {
+            continue;
+        }
+        let Some(path) = chain.find(&*list) else {
+            continue;
+        };
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| anyhow!("failed to read `{}`: {}", path.display(), e))?;
+        for (index, line) in content.lines().enumerate() {
+            let Ok(Entry {
+                item: Item::Run(script),
+                ..
+            }) = line.parse()
+            else {
+                continue;
+            };
+            if let Err(err) = parse(&script, index, line.len() - script.len()) {
+                problems.push(format!("{name}.airm/{list}: {err}"));
+            }
+        }
+    }
+
+    Ok(problems)
+}
+
+/// Parses a script at the given zero-based line and column of its file, adjusting position of the error accordingly.
+fn parse(script: &str, line: usize, column: usize) -> Result<Script, ParseError> {
+    Script::parse(script).map_err(|mut err| {
+        if err.line == 1 {
+            err.column += column;
+        }
+        err.line += line;
+        err
+    })
+}
+
+fn run(service: &str, script: &str, dry_run: bool) -> anyhow::Result<()> {
+    let mut conn = super::connect()?;
+
+    let commands = conn
+        .expand_ace(service, script)?
+        .map_err(|e| anyhow!("failed to expand command in service `{service}`: {e}"))?;
+    for argv in &commands {
+        let argv: Vec<_> = argv.iter().map(|x| display_arg(x)).collect();
+        eprintln!("{}", style(format!("+ {}", argv.join(" "))).dim());
+    }
+    if dry_run {
+        return Ok(());
+    }
+
+    let (stdin, stdout, stderr) = (std::io::stdin(), std::io::stdout(), std::io::stderr());
+    let stdio = [stdin.as_fd(), stdout.as_fd(), stderr.as_fd()];
+    let status = conn
+        .run_ace_in_service(service, script, stdio)?
+        .map_err(|e| anyhow!("failed to run in service `{service}`: {e}"))?;
+
+    std::process::exit(status.code());
+}
+
+/// Formats an argument for display, quoting it if it would not be parsed as a single word.
+fn display_arg(arg: &str) -> String {
+    let bare = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|x| x.is_ascii_alphanumeric() || "-_./:=,@%+".contains(x));
+    match bare {
+        true => arg.into(),
+        false => super::run::quote(arg),
+    }
+}
@@ -0,0 +1,317 @@
+use crate::util::format_cpu_time;
+use airup_sdk::{
+    blocking::Connection,
+    system::{ConnectionExt as _, QueryService, QuerySystem, ServiceStartReason, StartTimings},
+};
+use anyhow::anyhow;
+use clap::{Parser, Subcommand, ValueEnum};
+use console::style;
+use std::{collections::HashSet, fmt::Write};
+
+/// Width of the timeline area of exported SVG images, in pixels.
+const SVG_WIDTH: f64 = 1200.;
+
+/// Height of a row of exported SVG images, in pixels.
+const SVG_ROW_HEIGHT: f64 = 20.;
+
+/// Height of the header of exported SVG images, in pixels.
+const SVG_HEADER_HEIGHT: f64 = 40.;
+
+/// Analyze boot performance of the system
+#[derive(Debug, Clone, Parser)]
+#[command(about)]
+pub struct Cmdline {
+    #[command(subcommand)]
+    action: Action,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+enum Action {
+    /// List started services, ordered by time they took to become active
+    Blame,
+
+    /// Print the chain of services that delayed entering milestones the most
+    CriticalChain {
+        /// The milestone to analyze, all entered milestones by default
+        milestone: Option<String>,
+    },
+
+    /// Export a timeline of starting services to standard output
+    Timeline {
+        /// Format of the timeline
+        #[arg(long, value_enum, default_value_t = Format::Svg)]
+        format: Format,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Format {
+    Svg,
+    Json,
+}
+
+pub fn main(cmdline: Cmdline) -> anyhow::Result<()> {
+    let mut conn = super::connect()?;
+    let system = conn
+        .query_system()?
+        .map_err(|e| anyhow!("failed to query system: {e}"))?;
+    let services = started_services(&mut conn, &system)?;
+
+    match cmdline.action {
+        Action::Blame => blame(&services),
+        Action::CriticalChain { milestone } => critical_chain(&system, &services, milestone)?,
+        Action::Timeline {
+            format: Format::Svg,
+        } => print!("{}", timeline_svg(&system, &services)),
+        Action::Timeline {
+            format: Format::Json,
+        } => println!(
+            "{}",
+            serde_json::to_string_pretty(&timeline_json(&system, &services))?
+        ),
+    }
+
+    Ok(())
+}
+
+/// A cached service that has been started, with timings of its last start.
+struct Started {
+    queried: QueryService,
+    timings: StartTimings,
+}
+impl Started {
+    fn name(&self) -> &str {
+        &self.queried.definition.name
+    }
+}
+
+/// Queries cached services that have been started, ordered by the time their starts were requested.
+fn started_services(conn: &mut Connection, system: &QuerySystem) -> anyhow::Result<Vec<Started>> {
+    let mut services = Vec::with_capacity(system.services.len());
+    for name in &system.services {
+        let Ok(queried) = conn.query_service(name)? else {
+            continue;
+        };
+        if let Some(timings) = queried.start_timings.clone() {
+            services.push(Started { queried, timings });
+        }
+    }
+    services.sort_by_key(|x| x.timings.requested);
+
+    Ok(services)
+}
+
+fn blame(services: &[Started]) {
+    let mut durations: Vec<_> = services
+        .iter()
+        .filter_map(|x| Some((x.timings.activation_duration()?, x)))
+        .collect();
+    durations.sort_by_key(|(duration, _)| std::cmp::Reverse(*duration));
+
+    for (duration, service) in durations {
+        let pre_start = service
+            .timings
+            .pre_start_duration()
+            .map(|x| format!(" (pre-start {})", format_ms(x)))
+            .unwrap_or_default();
+        println!(
+            "{:>12} {}{}",
+            format_ms(duration),
+            service.name(),
+            style(pre_start).dim()
+        );
+    }
+}
+
+fn critical_chain(
+    system: &QuerySystem,
+    services: &[Started],
+    milestone: Option<String>,
+) -> anyhow::Result<()> {
+    let milestones: Vec<_> = match &milestone {
+        Some(name) => {
+            let name = name.strip_suffix(".airm").unwrap_or(name);
+            let entered = system
+                .milestones
+                .iter()
+                .rfind(|x| x.name == name)
+                .ok_or_else(|| anyhow!("milestone `{name}` is not entered"))?;
+            vec![entered]
+        }
+        None => system.milestones.iter().collect(),
+    };
+
+    println!("The time when the service became active is printed after the \"@\" character.");
+    println!("The time the service took to start is printed after the \"+\" character.");
+    for milestone in milestones {
+        println!();
+        println!(
+            "{} @{}",
+            style(&milestone.name).bold(),
+            format_ms(milestone.finish_timestamp - system.boot_timestamp)
+        );
+
+        let root = services
+            .iter()
+            .filter(|x| {
+                matches!(&x.queried.start_reason, Some(ServiceStartReason::Milestone(m)) if *m == milestone.name)
+            })
+            .filter(|x| x.timings.active.is_some())
+            .max_by_key(|x| x.timings.active);
+        let mut visited = HashSet::new();
+        let mut current = root;
+        let mut depth = 0;
+        while let Some(service) = current {
+            if !visited.insert(service.name()) {
+                break;
+            }
+            let active = service.timings.active.unwrap_or_default();
+            let duration = service
+                .timings
+                .activation_duration()
+                .map(|x| format!(" +{}", format_ms(x)))
+                .unwrap_or_default();
+            println!(
+                "{}└─ {} @{}{}",
+                "   ".repeat(depth),
+                service.name(),
+                format_ms(active - system.boot_timestamp),
+                style(duration).red()
+            );
+
+            let dependencies = &service.queried.definition.service.dependencies;
+            current = services
+                .iter()
+                .filter(|x| dependencies.iter().any(|dep| dep == x.name()))
+                .filter(|x| x.timings.active.is_some())
+                .max_by_key(|x| x.timings.active);
+            depth += 1;
+        }
+    }
+
+    Ok(())
+}
+
+fn timeline_json(system: &QuerySystem, services: &[Started]) -> serde_json::Value {
+    let services: Vec<_> = services
+        .iter()
+        .map(|x| {
+            serde_json::json!({
+                "name": x.name(),
+                "start_reason": x.queried.start_reason,
+                "timings": x.timings,
+            })
+        })
+        .collect();
+
+    serde_json::json!({
+        "boot_timestamp": system.boot_timestamp,
+        "booted_since": system.booted_since,
+        "milestones": system.milestones,
+        "services": services,
+    })
+}
+
+fn timeline_svg(system: &QuerySystem, services: &[Started]) -> String {
+    let origin = system.boot_timestamp;
+    let end = services
+        .iter()
+        .filter_map(|x| x.timings.active.or(x.timings.dependencies_satisfied))
+        .chain(system.milestones.iter().map(|x| x.finish_timestamp))
+        .chain(system.booted_since)
+        .max()
+        .unwrap_or(origin);
+    let total = (end - origin).max(1);
+    let scale = SVG_WIDTH / total as f64;
+    let pos = |timestamp: i64| (timestamp - origin).max(0) as f64 * scale;
+    let height = SVG_HEADER_HEIGHT + SVG_ROW_HEIGHT * services.len() as f64 + 10.;
+    let width = SVG_WIDTH + 300.;
+
+    let mut svg = String::new();
+    _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" font-family="sans-serif" font-size="12">"#
+    );
+    svg.push_str(
+        "<style>.wait{fill:#ddd}.activate{fill:#e55}.pre-start{fill:#fa3}.milestone{stroke:#36c;stroke-dasharray:4}</style>\n",
+    );
+
+    // Time axis
+    let interval = [100, 200, 500, 1000, 2000, 5000, 10000, 30000, 60000]
+        .into_iter()
+        .find(|x| total / x <= 10)
+        .unwrap_or(total / 10);
+    for tick in (0..=total).step_by(interval as usize) {
+        let x = tick as f64 * scale;
+        _ = writeln!(
+            svg,
+            r##"<line x1="{x}" y1="20" x2="{x}" y2="{height}" stroke="#eee"/><text x="{x}" y="15">{}</text>"##,
+            format_ms(tick)
+        );
+    }
+
+    for milestone in &system.milestones {
+        let x = pos(milestone.finish_timestamp);
+        _ = writeln!(
+            svg,
+            r##"<line class="milestone" x1="{x}" y1="20" x2="{x}" y2="{height}"/><text x="{}" y="30" fill="#36c">{}</text>"##,
+            x + 2.,
+            escape(&milestone.name)
+        );
+    }
+
+    for (index, service) in services.iter().enumerate() {
+        let y = SVG_HEADER_HEIGHT + SVG_ROW_HEIGHT * index as f64;
+        let timings = &service.timings;
+        let satisfied = timings.dependencies_satisfied.unwrap_or(timings.requested);
+        let mut bar = |class: &str, begin: i64, end: i64| {
+            _ = writeln!(
+                svg,
+                r#"<rect class="{class}" x="{}" y="{y}" width="{}" height="{}"/>"#,
+                pos(begin),
+                (pos(end) - pos(begin)).max(1.),
+                SVG_ROW_HEIGHT - 4.
+            );
+        };
+        if satisfied > timings.requested {
+            bar("wait", timings.requested, satisfied);
+        }
+        if let Some(active) = timings.active {
+            bar("activate", satisfied, active);
+        }
+        if let (Some(begin), Some(end)) = (timings.pre_start_began, timings.pre_start_finished) {
+            bar("pre-start", begin, end);
+        }
+
+        let label = match timings.activation_duration() {
+            Some(x) => format!("{} ({})", service.name(), format_ms(x)),
+            None => service.name().into(),
+        };
+        _ = writeln!(
+            svg,
+            r#"<text x="{}" y="{}">{}</text>"#,
+            pos(timings.active.unwrap_or(satisfied)) + 4.,
+            y + SVG_ROW_HEIGHT - 8.,
+            escape(&label)
+        );
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Formats a duration in milliseconds into human-readable string.
+fn format_ms(ms: i64) -> String {
+    match ms.max(0) {
+        x @ 0..=999 => format!("{x}ms"),
+        x => format_cpu_time(x as u64 * 1000),
+    }
+}
+
+/// Escapes a string to be placed in XML text.
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
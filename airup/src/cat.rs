@@ -0,0 +1,201 @@
+use airup_sdk::{
+    blocking::{files, fs::DirChain},
+    files::Service,
+    system::ConnectionExt as _,
+};
+use anyhow::anyhow;
+use clap::Parser;
+use console::style;
+use std::{collections::BTreeMap, path::PathBuf};
+
+/// Print the effective manifest of a service
+#[derive(Debug, Clone, Parser)]
+#[command(about)]
+pub struct Cmdline {
+    service: String,
+
+    /// Compare the definition that the service is running with the one on disk
+    #[arg(long)]
+    diff: bool,
+}
+
+pub fn main(cmdline: Cmdline) -> anyhow::Result<()> {
+    let name = cmdline
+        .service
+        .strip_suffix(".airs")
+        .unwrap_or(&cmdline.service);
+    let sources = sources_of(name)?;
+
+    if cmdline.diff {
+        diff(name, sources)
+    } else {
+        cat(&sources)
+    }
+}
+
+/// Returns paths of files that the service is merged from, in the order they are merged, like `airupd` does.
+pub fn sources_of(name: &str) -> anyhow::Result<Vec<PathBuf>> {
+    let manifest = airup_sdk::build::manifest();
+    let main = DirChain::new(&manifest.service_dir)
+        .find(format!("{name}.airs"))
+        .ok_or_else(|| anyhow!("service `{name}` was not found on disk"))?;
+    let patch = DirChain::new(&manifest.config_dir).find(format!("{name}.airs.airc"));
+
+    Ok(std::iter::once(main).chain(patch).collect())
+}
+
+/// Prints the merged manifest, with each key annotated by the file it came from.
+fn cat(sources: &[PathBuf]) -> anyhow::Result<()> {
+    let mut doc = toml::Value::Table(toml::Table::new());
+    let mut origins = Origins::new();
+    for (index, path) in sources.iter().enumerate() {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("failed to read `{}`: {}", path.display(), e))?;
+        let patch: toml::Value = toml::from_str(&content)
+            .map_err(|e| anyhow!("failed to parse `{}`: {}", path.display(), e))?;
+        origins.merge(&mut Vec::new(), &patch, index);
+        airup_sdk::files::merge(&mut doc, &patch);
+    }
+
+    let Some(table) = doc.as_table() else {
+        return Ok(());
+    };
+    print_table(&mut Vec::new(), table, &origins, sources);
+
+    Ok(())
+}
+
+/// Prints differences between the running definition of the service and the one on disk.
+fn diff(name: &str, sources: Vec<PathBuf>) -> anyhow::Result<()> {
+    let mut conn = super::connect()?;
+    let running = conn
+        .query_service(name)?
+        .map_err(|e| anyhow!("failed to query service `{}`: {}", name, e))?
+        .definition;
+    let on_disk: Service = files::read_merge(sources)
+        .map_err(|e| anyhow!("failed to read service `{}`: {}", name, e))?;
+
+    let running = flatten(&toml::Value::try_from(&running)?);
+    let on_disk = flatten(&toml::Value::try_from(&on_disk)?);
+
+    let mut keys: Vec<_> = running.keys().chain(on_disk.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    for key in keys {
+        match (running.get(key), on_disk.get(key)) {
+            (Some(x), Some(y)) if x == y => {}
+            (x, y) => {
+                if let Some(x) = x {
+                    println!("{}", style(format!("- {key} = {x}")).red());
+                }
+                if let Some(y) = y {
+                    println!("{}", style(format!("+ {key} = {y}")).green());
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Indices of the source files where keys of the merged manifest came from, keyed by paths of the keys.
+struct Origins(BTreeMap<Vec<String>, usize>);
+impl Origins {
+    fn new() -> Self {
+        Self(BTreeMap::new())
+    }
+
+    /// Records origins of keys in the patch, following the rules of [`airup_sdk::files::merge`].
+    fn merge(&mut self, path: &mut Vec<String>, patch: &toml::Value, index: usize) {
+        let Some(table) = patch.as_table() else {
+            self.remove_prefix(path);
+            self.0.insert(path.clone(), index);
+            return;
+        };
+        self.0.remove(path.as_slice());
+        for (key, value) in table {
+            path.push(key.clone());
+            if value.as_table().is_some_and(|x| x.is_empty()) {
+                self.remove_prefix(path);
+            } else {
+                self.merge(path, value, index);
+            }
+            path.pop();
+        }
+    }
+
+    fn remove_prefix(&mut self, prefix: &[String]) {
+        self.0.retain(|k, _| !k.starts_with(prefix));
+    }
+
+    fn get(&self, path: &[String]) -> Option<usize> {
+        self.0.get(path).copied()
+    }
+}
+
+fn print_table(
+    path: &mut Vec<String>,
+    table: &toml::Table,
+    origins: &Origins,
+    sources: &[PathBuf],
+) {
+    let (tables, values): (Vec<_>, Vec<_>) = table.iter().partition(|x| x.1.is_table());
+
+    if !path.is_empty() && !values.is_empty() {
+        let header: Vec<_> = path.iter().map(|x| key(x)).collect();
+        println!("[{}]", header.join("."));
+    }
+    for (k, v) in &values {
+        path.push(k.to_string());
+        let origin = origins
+            .get(path)
+            .map(|x| sources[x].display().to_string())
+            .unwrap_or_default();
+        println!("{} = {} {}", key(k), v, style(format!("# {origin}")).dim());
+        path.pop();
+    }
+    if !values.is_empty() {
+        println!();
+    }
+
+    for (k, v) in tables {
+        path.push(k.to_string());
+        print_table(path, v.as_table().unwrap(), origins, sources);
+        path.pop();
+    }
+}
+
+/// Flattens a TOML document into a map from dotted paths of keys to their values.
+fn flatten(value: &toml::Value) -> BTreeMap<String, String> {
+    fn walk(prefix: &str, value: &toml::Value, result: &mut BTreeMap<String, String>) {
+        match value.as_table() {
+            Some(table) => {
+                for (k, v) in table {
+                    let path = match prefix {
+                        "" => key(k),
+                        _ => format!("{prefix}.{}", key(k)),
+                    };
+                    walk(&path, v, result);
+                }
+            }
+            None => {
+                result.insert(prefix.into(), value.to_string());
+            }
+        }
+    }
+
+    let mut result = BTreeMap::new();
+    walk("", value, &mut result);
+    result
+}
+
+/// Formats a TOML key, quoting it if it is not a bare key.
+fn key(k: &str) -> String {
+    let bare = !k.is_empty()
+        && k.chars()
+            .all(|x| x.is_ascii_alphanumeric() || x == '-' || x == '_');
+    match bare {
+        true => k.into(),
+        false => toml::Value::String(k.into()).to_string(),
+    }
+}
@@ -59,8 +59,8 @@ pub fn main(cmdline: Cmdline) -> anyhow::Result<()> {
     let mut disabled = false;
 
     for x in old.lines() {
-        if let Ok(item) = x.parse::<milestone::Item>() {
-            match item {
+        if let Ok(entry) = x.parse::<milestone::Entry>() {
+            match entry.item {
                 milestone::Item::Start(x) if x.strip_suffix(".airs").unwrap_or(&x) == service => {
                     disabled = true;
                 }
@@ -57,8 +57,8 @@ pub fn main(cmdline: Cmdline) -> anyhow::Result<()> {
         Milestone::read_from(milestone).map_err(|x| anyhow!("failed to read milestone: {x}"))?;
     let chain = DirChain::new(&milestone.base_dir);
 
-    for item in milestone.items() {
-        match item {
+    for entry in milestone.items() {
+        match entry.item {
             milestone::Item::Start(x) if x.strip_suffix(".airs").unwrap_or(&x) == service => {
                 eprintln!(
                     "{} service {} have already been enabled",
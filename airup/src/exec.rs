@@ -0,0 +1,26 @@
+use anyhow::anyhow;
+use clap::Parser;
+use std::os::fd::AsFd;
+
+/// Run a command in a service's execution environment
+#[derive(Debug, Clone, Parser)]
+#[command(about)]
+pub struct Cmdline {
+    service: String,
+
+    /// The command to run, followed by its arguments
+    #[arg(last = true, required = true)]
+    command: Vec<String>,
+}
+
+pub fn main(cmdline: Cmdline) -> anyhow::Result<()> {
+    let mut conn = super::connect()?;
+
+    let (stdin, stdout, stderr) = (std::io::stdin(), std::io::stdout(), std::io::stderr());
+    let stdio = [stdin.as_fd(), stdout.as_fd(), stderr.as_fd()];
+    let status = conn
+        .exec_in_service(&cmdline.service, &cmdline.command, stdio)?
+        .map_err(|e| anyhow!("failed to execute in service `{}`: {}", cmdline.service, e))?;
+
+    std::process::exit(status.code());
+}
@@ -0,0 +1,59 @@
+use airup_sdk::system::{ConnectionExt as _, JobKind, JobState, RestartMode};
+use anyhow::anyhow;
+use clap::{Parser, Subcommand};
+
+/// Manage queued and running jobs
+#[derive(Debug, Clone, Parser)]
+#[command(about)]
+pub struct Cmdline {
+    #[command(subcommand)]
+    action: Action,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+enum Action {
+    /// List unfinished jobs
+    List,
+
+    /// Wait for a job to complete
+    Wait { id: u64 },
+
+    /// Cancel a job, interrupting it if it is running
+    Cancel { id: u64 },
+}
+
+pub fn main(cmdline: Cmdline) -> anyhow::Result<()> {
+    let mut conn = super::connect()?;
+
+    match cmdline.action {
+        Action::List => {
+            let jobs = conn.list_jobs()??;
+            println!("{:>8} {:<10} {:<20} SERVICE", "ID", "STATE", "KIND");
+            for job in jobs {
+                let state = match job.state {
+                    JobState::Waiting => "waiting",
+                    JobState::Running => "running",
+                };
+                let kind = match job.kind {
+                    JobKind::Start => "start",
+                    JobKind::Stop => "stop",
+                    JobKind::Reload => "reload",
+                    JobKind::Restart(RestartMode::Restart) => "restart",
+                    JobKind::Restart(RestartMode::TryRestart) => "try-restart",
+                    JobKind::Restart(RestartMode::ReloadOrRestart) => "reload-or-restart",
+                };
+                println!("{:>8} {:<10} {:<20} {}", job.id, state, kind, job.service);
+            }
+        }
+        Action::Wait { id } => {
+            conn.wait_job(id)?
+                .map_err(|e| anyhow!("job {id} failed: {e}"))?;
+        }
+        Action::Cancel { id } => {
+            conn.cancel_job(id)?
+                .map_err(|e| anyhow!("failed to cancel job {id}: {e}"))?;
+        }
+    }
+
+    Ok(())
+}
@@ -1,17 +1,26 @@
 //! Command-line utility for accessing Airup facilities.
 
+mod ace;
+mod analyze;
+mod cat;
 mod daemon;
 mod debug;
 mod disable;
 mod edit;
 mod enable;
+mod exec;
+mod job;
+mod milestone;
 mod query;
 mod reboot;
 mod reload;
 mod restart;
+mod run;
 mod self_reload;
+mod signal;
 mod start;
 mod stop;
+mod top;
 mod trigger_event;
 mod util;
 
@@ -27,9 +36,18 @@ enum Subcommand {
     Stop(stop::Cmdline),
     Reload(reload::Cmdline),
     Restart(restart::Cmdline),
+    Job(job::Cmdline),
+    Run(run::Cmdline),
+    Exec(exec::Cmdline),
+    Ace(ace::Cmdline),
     Query(query::Cmdline),
+    Cat(cat::Cmdline),
+    Signal(signal::Cmdline),
+    Top(top::Cmdline),
+    Analyze(analyze::Cmdline),
     SelfReload(self_reload::Cmdline),
     Reboot(reboot::Cmdline),
+    Milestone(milestone::Cmdline),
     Edit(edit::Cmdline),
     Enable(enable::Cmdline),
     Disable(disable::Cmdline),
@@ -44,8 +62,17 @@ impl Subcommand {
             Self::Stop(cmdline) => stop::main(cmdline),
             Self::Reload(cmdline) => reload::main(cmdline),
             Self::Restart(cmdline) => restart::main(cmdline),
+            Self::Job(cmdline) => job::main(cmdline),
+            Self::Run(cmdline) => run::main(cmdline),
+            Self::Exec(cmdline) => exec::main(cmdline),
+            Self::Ace(cmdline) => ace::main(cmdline),
             Self::Query(cmdline) => query::main(cmdline),
+            Self::Cat(cmdline) => cat::main(cmdline),
+            Self::Signal(cmdline) => signal::main(cmdline),
+            Self::Top(cmdline) => top::main(cmdline),
+            Self::Analyze(cmdline) => analyze::main(cmdline),
             Self::Reboot(cmdline) => reboot::main(cmdline),
+            Self::Milestone(cmdline) => milestone::main(cmdline),
             Self::SelfReload(cmdline) => self_reload::main(cmdline),
             Self::Edit(cmdline) => edit::main(cmdline),
             Self::Enable(cmdline) => enable::main(cmdline),
@@ -0,0 +1,134 @@
+use airup_sdk::{
+    files::milestone::{Kind, OnFailure},
+    system::{ConnectionExt as _, QueryMilestone},
+};
+use anyhow::anyhow;
+use chrono::prelude::*;
+use clap::{Parser, Subcommand};
+use console::style;
+
+/// Manage milestones
+#[derive(Debug, Clone, Parser)]
+#[command(about)]
+pub struct Cmdline {
+    #[command(subcommand)]
+    action: Action,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+enum Action {
+    /// List installed milestones
+    List,
+
+    /// Show details of a milestone
+    Show { name: String },
+
+    /// Enter a milestone
+    Enter {
+        name: String,
+
+        /// Stop all services that are not required by the milestone after entering it
+        #[arg(long)]
+        isolate: bool,
+    },
+}
+
+pub fn main(cmdline: Cmdline) -> anyhow::Result<()> {
+    let mut conn = super::connect()?;
+
+    match cmdline.action {
+        Action::List => {
+            let mut names = conn.list_milestones()??;
+            names.sort_unstable();
+            println!("{:<24} {:<8} DESCRIPTION", "NAME", "ENTERED");
+            for name in names {
+                let Ok(queried) = conn.query_milestone(&name)? else {
+                    continue;
+                };
+                let entered = match queried.entered {
+                    Some(_) => "yes",
+                    None => "no",
+                };
+                let description = queried.manifest.milestone.description.unwrap_or_default();
+                println!("{name:<24} {entered:<8} {description}");
+            }
+        }
+        Action::Show { name } => {
+            let queried = conn
+                .query_milestone(&name)?
+                .map_err(|e| anyhow!("failed to query milestone `{name}`: {e}"))?;
+            print_query_milestone(&queried);
+        }
+        Action::Enter { name, isolate } => {
+            let result = match isolate {
+                true => conn.isolate_milestone(&name)?,
+                false => conn.enter_milestone(&name)?,
+            };
+            result.map_err(|e| anyhow!("failed to enter milestone `{name}`: {e}"))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Prints a [`QueryMilestone`] to console, in human-friendly format.
+fn print_query_milestone(queried: &QueryMilestone) {
+    let metadata = &queried.manifest.milestone;
+    let display_name = metadata.display_name.as_deref().unwrap_or(&queried.name);
+    println!("{} ({})", style(display_name).bold(), queried.name);
+
+    if let Some(x) = &metadata.description {
+        println!("{:>14} {}", "Description:", x);
+    }
+    let kind = match metadata.kind {
+        Kind::Async => "async",
+        Kind::Sync => "sync",
+        Kind::Serial => "serial",
+    };
+    println!("{:>14} {}", "Kind:", kind);
+    if metadata.isolate {
+        println!("{:>14} yes", "Isolate:");
+    }
+    match metadata.on_failure {
+        OnFailure::Continue => {}
+        OnFailure::Fallback => {
+            println!("{:>14} fallback to {}", "On Failure:", metadata.fallback())
+        }
+        OnFailure::Reboot => println!("{:>14} reboot", "On Failure:"),
+    }
+    if !metadata.dependencies.is_empty() {
+        println!(
+            "{:>14} {}",
+            "Dependencies:",
+            metadata.dependencies.join(", ")
+        );
+    }
+    match &queried.entered {
+        Some(x) => {
+            let finished = DateTime::from_timestamp_millis(x.finish_timestamp)
+                .map(|x| Local.from_utc_datetime(&x.naive_utc()).to_string())
+                .unwrap_or_default();
+            println!(
+                "{:>14} at {} (took {}ms)",
+                "Entered:",
+                finished,
+                x.finish_timestamp - x.begin_timestamp
+            );
+        }
+        None => println!("{:>14} no", "Entered:"),
+    }
+
+    if !queried.items.is_empty() {
+        println!("\n{}", style("Items:").bold().underlined());
+        for item in &queried.items {
+            println!("{item}");
+        }
+    }
+
+    if !queried.services.is_empty() {
+        println!("\n{}", style("Started Services:").bold().underlined());
+        for service in &queried.services {
+            println!("{service}");
+        }
+    }
+}
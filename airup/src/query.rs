@@ -1,7 +1,10 @@
 use airup_sdk::{
     blocking::Connection,
     extapi::ConnectionExt,
-    system::{ConnectionExt as _, QueryService, QuerySystem, ServiceStartReason, Status},
+    system::{
+        ConnectionExt as _, ExitStatus, QueryService, QuerySystem, ServiceProcess,
+        ServiceStartReason, Status,
+    },
 };
 use anyhow::anyhow;
 use chrono::prelude::*;
@@ -20,6 +23,10 @@ pub struct Cmdline {
     #[arg(long)]
     fetch_log: Option<u32>,
 
+    /// Lists processes belonging to the service
+    #[arg(short, long, requires = "selector")]
+    processes: bool,
+
     selector: Option<String>,
 }
 
@@ -61,6 +68,18 @@ fn query_service(
         .map_err(|e| anyhow!("failed to query service `{}`: {}", service_name, e))?;
     print_query_service(&queried);
 
+    if cmdline.processes {
+        let processes = conn.list_service_processes(service_name)?.map_err(|e| {
+            anyhow!(
+                "failed to list processes of service `{}`: {}",
+                service_name,
+                e
+            )
+        })?;
+        println!("\n{}", style("Processes:").bold().underlined());
+        print_processes(&processes);
+    }
+
     if let Ok(Ok(logs)) = conn.tail_logs(&format!("airup_service_{}", service_name), n as _) {
         println!("\n{}", style("Logs:").bold().underlined());
         for log in logs {
@@ -103,7 +122,16 @@ fn print_query_service(query_service: &QueryService) {
             .unwrap_or_else(|| format!("{}", style("(null)").dim()))
     );
 
-    if let PrintedStatusKind::Active | PrintedStatusKind::Starting = status.kind {
+    if let Some(x) = &query_service.watchdog {
+        println!(
+            "{:>14} {} consecutive failures, {} consecutive successes",
+            "Watchdog:", x.consecutive_failures, x.consecutive_successes
+        );
+    }
+
+    if let PrintedStatusKind::Active | PrintedStatusKind::Degraded | PrintedStatusKind::Starting =
+        status.kind
+    {
         let reason = match &query_service.start_reason {
             Some(ServiceStartReason::Manual) => "manual".into(),
             Some(ServiceStartReason::Dependency(dep)) => format!("dependency of `{dep}`"),
@@ -113,9 +141,74 @@ fn print_query_service(query_service: &QueryService) {
         println!("{:>14} {}", "Start Reason:", reason,);
     }
 
+    if query_service.restart_count != 0 {
+        let last = query_service
+            .last_restart
+            .and_then(|x| DateTime::from_timestamp(x / 1000, 0))
+            .map(|x| format!("; last at {}", Local.from_utc_datetime(&x.naive_utc())))
+            .unwrap_or_default();
+        println!(
+            "{:>14} {}{}",
+            "Restarts:", query_service.restart_count, last
+        );
+    }
+
+    match query_service.last_exit {
+        Some(ExitStatus::Exited(code)) => {
+            println!("{:>14} exited with code {}", "Last Exit:", code)
+        }
+        Some(ExitStatus::Signaled(signum)) => {
+            println!("{:>14} terminated by signal {}", "Last Exit:", signum)
+        }
+        None => (),
+    }
+
     if let Some(x) = &query_service.memory_usage {
         println!("{:>14} {}", "Memory:", crate::util::format_size(*x));
     }
+
+    if let Some(x) = &query_service.resource_usage {
+        if let Some(peak) = x.memory_peak {
+            println!("{:>14} {}", "Memory Peak:", crate::util::format_size(peak));
+        }
+        println!(
+            "{:>14} {} (user {}, system {})",
+            "CPU:",
+            crate::util::format_cpu_time(x.cpu_user + x.cpu_system),
+            crate::util::format_cpu_time(x.cpu_user),
+            crate::util::format_cpu_time(x.cpu_system),
+        );
+        println!(
+            "{:>14} {} read, {} written",
+            "IO:",
+            crate::util::format_size(x.io_read),
+            crate::util::format_size(x.io_write),
+        );
+        println!("{:>14} {}", "Tasks:", x.tasks);
+    }
+}
+
+/// Prints a list of [`ServiceProcess`] to console, in human-friendly format.
+fn print_processes(processes: &[ServiceProcess]) {
+    println!("{:>8} {:>8} {:<19} COMMAND", "PID", "UID", "STARTED");
+    for process in processes {
+        let started = DateTime::from_timestamp_millis(process.start_time)
+            .map(|x| {
+                Local
+                    .from_utc_datetime(&x.naive_utc())
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string()
+            })
+            .unwrap_or_default();
+        let command = match process.cmdline.is_empty() {
+            true => format!("[{}]", process.name),
+            false => process.cmdline.join(" "),
+        };
+        println!(
+            "{:>8} {:>8} {:<19} {}",
+            process.pid, process.uid, started, command
+        );
+    }
 }
 
 /// Prints a [`QuerySystem`] to console, in human-friendly format.
@@ -146,6 +239,16 @@ fn print_query_system(
     let status = PrintedStatus::of_system(query_system);
     println!("{} {}", status.theme_dot(), query_system.instance_name);
     println!("{:>14} {}", "Status:", status);
+    for (index, failed) in query_system.failed_items.iter().enumerate() {
+        let label = if index == 0 { "Failed:" } else { "" };
+        println!(
+            "{:>14} {} ({}): {}",
+            label,
+            failed.item,
+            failed.milestone,
+            style(&failed.error).red()
+        );
+    }
     println!("{:>14} /", "Services:");
     for (name, status) in &services {
         match status {
@@ -165,7 +268,9 @@ fn print_query_system(
 #[derive(Debug, Clone)]
 enum PrintedStatusKind {
     Active,
+    Degraded,
     Stopped,
+    Skipped,
     Failed,
     Starting,
     Stopping,
@@ -176,12 +281,20 @@ impl PrintedStatusKind {
             Status::Active => Self::Active,
             Status::Stopped => Self::Stopped,
         };
+        if query_service.status == Status::Stopped && query_service.skipped.is_some() {
+            result = Self::Skipped;
+        }
+        if query_service.status == Status::Active
+            && query_service.watchdog.as_ref().is_some_and(|x| x.degraded)
+        {
+            result = Self::Degraded;
+        }
         if query_service.last_error.is_some() {
             result = Self::Failed;
         }
         if let Some(x) = query_service.task_class.as_deref() {
             match x {
-                "StartService" => result = Self::Starting,
+                "StartService" | "RestartService" => result = Self::Starting,
                 "StopService" => result = Self::Stopping,
                 _ => {}
             }
@@ -194,6 +307,9 @@ impl PrintedStatusKind {
         if query_system.is_booting {
             return Self::Starting;
         }
+        if query_system.is_degraded {
+            return Self::Degraded;
+        }
 
         match query_system.status {
             Status::Active => Self::Active,
@@ -205,7 +321,9 @@ impl PrintedStatusKind {
         let theme_dot = style(Emoji("●", "*"));
         match self {
             Self::Active => theme_dot.green(),
+            Self::Degraded => theme_dot.yellow(),
             Self::Stopped => theme_dot,
+            Self::Skipped => theme_dot.yellow(),
             Self::Failed => theme_dot.red(),
             Self::Starting | Self::Stopping => theme_dot.blue(),
         }
@@ -216,7 +334,9 @@ impl Display for PrintedStatusKind {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::Active => write!(f, "{}", style("active").bold().green()),
+            Self::Degraded => write!(f, "{}", style("degraded").bold().yellow()),
             Self::Stopped => write!(f, "{}", style("stopped").bold()),
+            Self::Skipped => write!(f, "{}", style("skipped").bold().yellow()),
             Self::Failed => write!(f, "{}", style("failed").bold().red()),
             Self::Starting => write!(f, "{}", style("starting").bold().blue()),
             Self::Stopping => write!(f, "{}", style("stopping").bold().blue()),
@@ -233,7 +353,10 @@ struct PrintedStatus {
 impl PrintedStatus {
     fn of_service(query_service: &QueryService) -> Self {
         let kind = PrintedStatusKind::of_service(query_service);
-        let error = query_service.last_error.as_ref().map(ToString::to_string);
+        let error = match kind {
+            PrintedStatusKind::Skipped => query_service.skipped.clone(),
+            _ => query_service.last_error.as_ref().map(ToString::to_string),
+        };
         let since = query_service.status_since;
         Self { kind, since, error }
     }
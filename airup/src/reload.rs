@@ -1,4 +1,4 @@
-use airup_sdk::system::ConnectionExt as _;
+use airup_sdk::system::{ConnectionExt as _, JobKind};
 use anyhow::anyhow;
 use clap::Parser;
 
@@ -7,6 +7,10 @@ use clap::Parser;
 #[command(about)]
 pub struct Cmdline {
     service: String,
+
+    /// Submit a job to reload the service and print its ID, without waiting for it to complete
+    #[arg(long)]
+    no_block: bool,
 }
 
 pub fn main(cmdline: Cmdline) -> anyhow::Result<()> {
@@ -25,6 +29,14 @@ pub fn main(cmdline: Cmdline) -> anyhow::Result<()> {
         }
     }
 
+    if cmdline.no_block {
+        let id = conn
+            .submit_job(&cmdline.service, JobKind::Reload)?
+            .map_err(|e| anyhow!("failed to reload service `{}`: {}", cmdline.service, e))?;
+        println!("{id}");
+        return Ok(());
+    }
+
     conn.reload_service(&cmdline.service)?
         .map_err(|e| anyhow!("failed to reload service `{}`: {}", cmdline.service, e))?;
 
@@ -1,4 +1,4 @@
-use airup_sdk::system::ConnectionExt as _;
+use airup_sdk::system::{ConnectionExt as _, JobKind, RestartMode};
 use anyhow::anyhow;
 use clap::Parser;
 
@@ -11,17 +11,36 @@ pub struct Cmdline {
     /// Restart the service if already started, otherwise start it
     #[arg(short = 'E', long)]
     effective: bool,
+
+    /// Reload the service if it supports reloading, otherwise restart it like `--effective`
+    #[arg(short, long, conflicts_with = "effective")]
+    reload: bool,
+
+    /// Submit a job to restart the service and print its ID, without waiting for it to complete
+    #[arg(long)]
+    no_block: bool,
 }
 
 pub fn main(cmdline: Cmdline) -> anyhow::Result<()> {
     let mut conn = super::connect()?;
 
-    let stop = conn.stop_service(&cmdline.service)?;
-    if !cmdline.effective {
-        stop.map_err(|e| anyhow!("failed to stop service `{}`: {}", cmdline.service, e))?;
+    let mode = if cmdline.reload {
+        RestartMode::ReloadOrRestart
+    } else if cmdline.effective {
+        RestartMode::TryRestart
+    } else {
+        RestartMode::Restart
+    };
+
+    if cmdline.no_block {
+        let id = conn
+            .submit_job(&cmdline.service, JobKind::Restart(mode))?
+            .map_err(|e| anyhow!("failed to restart service `{}`: {}", cmdline.service, e))?;
+        println!("{id}");
+        return Ok(());
     }
 
-    conn.start_service(&cmdline.service)?
-        .map_err(|e| anyhow!("failed to start service `{}`: {}", cmdline.service, e))?;
+    conn.restart_service(&cmdline.service, mode)?
+        .map_err(|e| anyhow!("failed to restart service `{}`: {}", cmdline.service, e))?;
     Ok(())
 }
@@ -0,0 +1,194 @@
+use airup_sdk::{
+    Error,
+    blocking::Connection,
+    extapi::ConnectionExt as _,
+    files::Service,
+    system::{ConnectionExt as _, ExitStatus, Status},
+};
+use anyhow::{anyhow, bail};
+use clap::Parser;
+use std::time::Duration;
+
+/// Run a command as a transient service
+#[derive(Debug, Clone, Parser)]
+#[command(about)]
+pub struct Cmdline {
+    /// Name of the transient service, `run-<PID>` by default
+    #[arg(long)]
+    name: Option<String>,
+
+    /// UID to run the command as
+    #[arg(long)]
+    uid: Option<u32>,
+
+    /// Set an environment variable for the command
+    #[arg(long, value_name = "KEY=VALUE", value_parser = parse_env)]
+    env: Vec<(String, String)>,
+
+    /// Max memory usage of the service, e.g. `512M` or `1G`
+    #[arg(long, value_parser = crate::util::parse_size)]
+    memory: Option<u64>,
+
+    /// Run the command as a `oneshot` service, which is started when the command exits successfully
+    #[arg(long)]
+    oneshot: bool,
+
+    /// Stream logs of the service and wait for it to exit, then uncache it and exit with its exit code
+    #[arg(long)]
+    wait: bool,
+
+    /// The command to run, followed by its arguments
+    #[arg(last = true, required = true)]
+    command: Vec<String>,
+}
+impl Cmdline {
+    /// Builds the transient service in memory.
+    fn service(&self) -> anyhow::Result<Service> {
+        let command: Vec<_> = self.command.iter().map(|x| quote(x)).collect();
+        let vars: serde_json::Map<_, _> = self
+            .env
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone().into()))
+            .collect();
+        let service = serde_json::json!({
+            "service": {
+                "description": format!("Transient service for `{}`", self.command.join(" ")),
+                "kind": if self.oneshot { "oneshot" } else { "simple" },
+            },
+            "exec": { "start": command.join(" ") },
+            "env": { "uid": self.uid, "vars": vars },
+            "reslimit": { "memory": self.memory },
+        });
+
+        serde_json::from_value(service).map_err(|e| anyhow!("failed to build service: {e}"))
+    }
+}
+
+pub fn main(cmdline: Cmdline) -> anyhow::Result<()> {
+    let mut conn = super::connect()?;
+
+    let name = cmdline
+        .name
+        .clone()
+        .unwrap_or_else(|| format!("run-{}", std::process::id()));
+    if conn.query_service(&name)?.is_ok() {
+        bail!("service `{name}` already exists");
+    }
+
+    let service = cmdline.service()?;
+    conn.sideload_service(&name, &service)?
+        .map_err(|e| anyhow!("failed to sideload service `{name}`: {e}"))?;
+
+    if !cmdline.wait {
+        if let Err(err) = conn.start_service(&name)? {
+            _ = conn.uncache_service(&name)?;
+            bail!("failed to start service `{name}`: {err}");
+        }
+        println!("{name}");
+        return Ok(());
+    }
+
+    let result = run_wait(&mut conn, &name, cmdline.oneshot);
+    if cmdline.oneshot {
+        _ = conn.stop_service(&name)?;
+    }
+    conn.uncache_service(&name)?
+        .map_err(|e| anyhow!("failed to uncache service `{name}`: {e}"))?;
+
+    std::process::exit(result?);
+}
+
+/// Starts the service, streams its logs and waits for it to exit. Returns the exit code.
+fn run_wait(conn: &mut Connection, name: &str, oneshot: bool) -> anyhow::Result<i32> {
+    let mut logs = LogFollower::new(name);
+
+    if let Err(err) = conn.start_service(name)? {
+        logs.follow(conn);
+        return match err {
+            Error::Exited { exit_code } => Ok(exit_code),
+            Error::Signaled { signum } => Ok(ExitStatus::Signaled(signum).code()),
+            err => Err(anyhow!("failed to start service `{name}`: {err}")),
+        };
+    }
+    if oneshot {
+        logs.follow(conn);
+        return Ok(0);
+    }
+
+    loop {
+        logs.follow(conn);
+        let queried = conn
+            .query_service(name)?
+            .map_err(|e| anyhow!("failed to query service `{name}`: {e}"))?;
+        if queried.status == Status::Stopped && queried.task_class.is_none() {
+            logs.follow(conn);
+            return Ok(queried.last_exit.map(ExitStatus::code).unwrap_or_default());
+        }
+        std::thread::sleep(Duration::from_millis(200));
+    }
+}
+
+/// A helper to print log records of a service that are not printed yet.
+struct LogFollower {
+    subject: String,
+    since: i64,
+    last: Option<(i64, String)>,
+}
+impl LogFollower {
+    fn new(name: &str) -> Self {
+        Self {
+            subject: format!("airup_service_{name}"),
+            since: chrono::Utc::now().timestamp_millis(),
+            last: None,
+        }
+    }
+
+    /// Prints new log records. Errors are ignored, since the logger may be unavailable.
+    fn follow(&mut self, conn: &mut Connection) {
+        let Ok(Ok(records)) = conn.tail_logs(&self.subject, 512) else {
+            return;
+        };
+        let start = self.last.as_ref().map_or(0, |(timestamp, message)| {
+            records
+                .iter()
+                .rposition(|x| x.timestamp == *timestamp && x.message == *message)
+                .map_or(0, |x| x + 1)
+        });
+        for record in records[start..]
+            .iter()
+            .filter(|x| x.timestamp >= self.since)
+        {
+            println!("{}", record.message);
+        }
+        if let Some(x) = records.last() {
+            self.last = Some((x.timestamp, x.message.clone()));
+        }
+    }
+}
+
+/// Quotes an argument as an ACE string literal.
+pub fn quote(arg: &str) -> String {
+    let mut quoted = String::with_capacity(arg.len() + 2);
+    quoted.push('"');
+    for c in arg.chars() {
+        match c {
+            '"' | '\\' | '$' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            '\0' => quoted.push_str("\\0"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn parse_env(s: &str) -> Result<(String, String), String> {
+    s.split_once('=')
+        .map(|(k, v)| (k.into(), v.into()))
+        .ok_or_else(|| format!("invalid environment variable `{s}`, expected `KEY=VALUE`"))
+}
@@ -0,0 +1,31 @@
+use airup_sdk::system::ConnectionExt as _;
+use anyhow::anyhow;
+use clap::Parser;
+
+/// Send a signal to a service
+#[derive(Debug, Clone, Parser)]
+#[command(about)]
+pub struct Cmdline {
+    service: String,
+
+    /// Signal to send, either a number or a name like `HUP` or `SIGHUP`
+    #[arg(value_parser = parse_signal)]
+    signal: i32,
+
+    /// Send the signal to all processes of the service, instead of the main process only
+    #[arg(short, long)]
+    all: bool,
+}
+
+pub fn main(cmdline: Cmdline) -> anyhow::Result<()> {
+    let mut conn = super::connect()?;
+
+    conn.signal_service(&cmdline.service, cmdline.signal, cmdline.all)?
+        .map_err(|e| anyhow!("failed to signal service `{}`: {}", cmdline.service, e))?;
+
+    Ok(())
+}
+
+fn parse_signal(s: &str) -> Result<i32, String> {
+    airup_sdk::signal::parse(s).ok_or_else(|| format!("unknown signal `{s}`"))
+}
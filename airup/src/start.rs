@@ -1,4 +1,7 @@
-use airup_sdk::{blocking::files, system::ConnectionExt as _};
+use airup_sdk::{
+    blocking::files,
+    system::{ConnectionExt as _, JobKind},
+};
 use anyhow::anyhow;
 use clap::Parser;
 use std::path::PathBuf;
@@ -16,6 +19,10 @@ pub struct Cmdline {
     /// Sideload a service
     #[arg(long)]
     sideload: Option<PathBuf>,
+
+    /// Submit a job to start the service and print its ID, without waiting for it to complete
+    #[arg(long)]
+    no_block: bool,
 }
 
 pub fn main(cmdline: Cmdline) -> anyhow::Result<()> {
@@ -27,7 +34,12 @@ pub fn main(cmdline: Cmdline) -> anyhow::Result<()> {
         conn.sideload_service(&cmdline.service, &service)??;
     }
 
-    if !cmdline.cache {
+    if cmdline.no_block && !cmdline.cache {
+        let id = conn
+            .submit_job(&cmdline.service, JobKind::Start)?
+            .map_err(|e| anyhow!("failed to start service `{}`: {}", cmdline.service, e))?;
+        println!("{id}");
+    } else if !cmdline.cache {
         conn.start_service(&cmdline.service)?
             .map_err(|e| anyhow!("failed to start service `{}`: {}", cmdline.service, e))?;
     } else if cmdline.sideload.is_none() {
@@ -1,4 +1,4 @@
-use airup_sdk::system::ConnectionExt as _;
+use airup_sdk::system::{ConnectionExt as _, JobKind};
 use anyhow::anyhow;
 use clap::Parser;
 
@@ -15,11 +15,23 @@ pub struct Cmdline {
     /// Force the service to stop
     #[arg(short, long)]
     force: bool,
+
+    /// Submit a job to stop the service and print its ID, without waiting for it to complete
+    #[arg(long, conflicts_with_all = ["force", "uncache"])]
+    no_block: bool,
 }
 
 pub fn main(cmdline: Cmdline) -> anyhow::Result<()> {
     let mut conn = super::connect()?;
 
+    if cmdline.no_block {
+        let id = conn
+            .submit_job(&cmdline.service, JobKind::Stop)?
+            .map_err(|e| anyhow!("failed to stop service `{}`: {}", cmdline.service, e))?;
+        println!("{id}");
+        return Ok(());
+    }
+
     let mut stop_service = || {
         if cmdline.force {
             conn.kill_service(&cmdline.service)
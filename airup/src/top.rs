@@ -0,0 +1,159 @@
+use airup_sdk::{
+    blocking::Connection,
+    system::{ConnectionExt as _, QueryService, Status},
+};
+use chrono::prelude::*;
+use clap::{Parser, ValueEnum};
+use console::{Term, style};
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+/// Show resource usage of services in a live view
+#[derive(Debug, Clone, Parser)]
+#[command(about)]
+pub struct Cmdline {
+    /// Refresh interval, in milliseconds
+    #[arg(short, long, default_value_t = 2000)]
+    delay: u64,
+
+    /// Exit after refreshing the specified times
+    #[arg(short = 'n', long)]
+    iterations: Option<u64>,
+
+    /// Column to sort services by
+    #[arg(short, long, default_value = "cpu")]
+    sort: SortBy,
+
+    /// Includes services that are not running
+    #[arg(short, long)]
+    all: bool,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum SortBy {
+    Name,
+    Cpu,
+    Memory,
+    Io,
+    Tasks,
+}
+
+pub fn main(cmdline: Cmdline) -> anyhow::Result<()> {
+    let mut conn = super::connect()?;
+    let term = Term::stdout();
+    let mut last: HashMap<String, u64> = HashMap::new();
+    let mut last_instant = Instant::now();
+    let mut iteration = 0;
+
+    loop {
+        let rows = collect(&mut conn, &cmdline)?;
+        let elapsed = last_instant.elapsed().as_micros().max(1) as u64;
+        last_instant = Instant::now();
+
+        let mut rows: Vec<_> = rows
+            .into_iter()
+            .map(|x| {
+                let cpu_time = x
+                    .resource_usage
+                    .as_ref()
+                    .map(|x| x.cpu_user + x.cpu_system)
+                    .unwrap_or_default();
+                let cpu_percent = last
+                    .get(&x.definition.name)
+                    .map(|&prev| cpu_time.saturating_sub(prev) as f64 * 100. / elapsed as f64);
+                last.insert(x.definition.name.clone(), cpu_time);
+                (x, cpu_percent)
+            })
+            .collect();
+        sort(&mut rows, cmdline.sort);
+
+        _ = term.clear_screen();
+        print(&term, &rows);
+
+        iteration += 1;
+        if cmdline.iterations.is_some_and(|x| iteration >= x) {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(cmdline.delay));
+    }
+
+    Ok(())
+}
+
+/// Queries services to show.
+fn collect(conn: &mut Connection, cmdline: &Cmdline) -> anyhow::Result<Vec<QueryService>> {
+    let mut result = Vec::new();
+    for name in conn.query_system()??.services {
+        let Ok(queried) = conn.query_service(&name)? else {
+            continue;
+        };
+        if cmdline.all || queried.status == Status::Active || queried.resource_usage.is_some() {
+            result.push(queried);
+        }
+    }
+
+    Ok(result)
+}
+
+fn sort(rows: &mut [(QueryService, Option<f64>)], by: SortBy) {
+    let usage = |x: &QueryService| x.resource_usage.clone().unwrap_or_default();
+    match by {
+        SortBy::Name => rows.sort_by(|a, b| a.0.definition.name.cmp(&b.0.definition.name)),
+        SortBy::Cpu => {
+            rows.sort_by(|a, b| b.1.unwrap_or_default().total_cmp(&a.1.unwrap_or_default()))
+        }
+        SortBy::Memory => rows.sort_by_key(|x| std::cmp::Reverse(x.0.memory_usage)),
+        SortBy::Io => rows.sort_by_key(|x| {
+            let usage = usage(&x.0);
+            std::cmp::Reverse(usage.io_read + usage.io_write)
+        }),
+        SortBy::Tasks => rows.sort_by_key(|x| std::cmp::Reverse(usage(&x.0).tasks)),
+    }
+}
+
+fn print(term: &Term, rows: &[(QueryService, Option<f64>)]) {
+    let now = Local::now().format("%H:%M:%S");
+    println!(
+        "{} - {} services",
+        style(format!("airup top - {now}")).bold(),
+        rows.len()
+    );
+    println!();
+    println!(
+        "{}",
+        style(format!(
+            "{:<24} {:<8} {:>8} {:>6} {:>10} {:>10} {:>6} {:>10} {:>10}",
+            "SERVICE", "STATUS", "PID", "CPU%", "MEMORY", "PEAK", "TASKS", "READ", "WRITE"
+        ))
+        .reverse()
+    );
+
+    let max_rows = term.size().0.saturating_sub(4) as usize;
+    for (queried, cpu_percent) in rows.iter().take(max_rows) {
+        let usage = queried.resource_usage.clone().unwrap_or_default();
+        let status = match queried.status {
+            Status::Active => style("active").green(),
+            Status::Stopped => style("stopped").dim(),
+        };
+        let or_dash = |x: Option<String>| x.unwrap_or_else(|| "-".into());
+        let mut name = queried.definition.name.clone();
+        if name.chars().count() > 24 {
+            name = name.chars().take(23).chain(std::iter::once('~')).collect();
+        }
+
+        println!(
+            "{:<24} {:<8} {:>8} {:>6} {:>10} {:>10} {:>6} {:>10} {:>10}",
+            name,
+            status,
+            or_dash(queried.pid.map(|x| x.to_string())),
+            or_dash(cpu_percent.map(|x| format!("{x:.1}"))),
+            or_dash(queried.memory_usage.map(crate::util::format_size)),
+            or_dash(usage.memory_peak.map(crate::util::format_size)),
+            usage.tasks,
+            crate::util::format_size(usage.io_read),
+            crate::util::format_size(usage.io_write),
+        );
+    }
+}
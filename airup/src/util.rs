@@ -7,3 +7,35 @@ pub fn format_size(bytes: u64) -> String {
         10_000_000_000.. => format!("{:.2} GB", ((bytes as f64) / 1_000_000_000.)),
     }
 }
+
+/// Formats a CPU time in microseconds into human-readable string.
+pub fn format_cpu_time(us: u64) -> String {
+    match us {
+        0..=999 => format!("{us}us"),
+        1000..=999_999 => format!("{}ms", us / 1000),
+        1_000_000..=59_999_999 => format!("{:.3}s", (us as f64) / 1_000_000.),
+        60_000_000.. => format!(
+            "{}min {:.3}s",
+            us / 60_000_000,
+            ((us % 60_000_000) as f64) / 1_000_000.
+        ),
+    }
+}
+
+/// Parses a human-readable size string, like `512K`, `64M` or `1G`, into byte number. Suffixes are binary units, and a
+/// string without a suffix is in bytes.
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let (num, multiplier) = match s.char_indices().last() {
+        Some((i, 'K' | 'k')) => (&s[..i], 1 << 10),
+        Some((i, 'M' | 'm')) => (&s[..i], 1 << 20),
+        Some((i, 'G' | 'g')) => (&s[..i], 1 << 30),
+        Some((i, 'T' | 't')) => (&s[..i], 1 << 40),
+        _ => (s, 1),
+    };
+    num.trim()
+        .parse::<u64>()
+        .ok()
+        .and_then(|x| x.checked_mul(multiplier))
+        .ok_or_else(|| format!("invalid size `{s}`"))
+}
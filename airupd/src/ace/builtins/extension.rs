@@ -0,0 +1,35 @@
+//! Commands provided by extensions.
+//!
+//! A command named `ext.<extension>.<method>` invokes method `<method>` of extension `<extension>`, with its arguments passed
+//! as a list of strings. If the method returned successfully, the command exits with code `0`. If the method failed with an
+//! `EXITED` error, its exit code is used. Otherwise, the command exits with `127` if the extension or the method does not
+//! exist, `1` if parameters are rejected by the method, or `2` if any other error occured.
+
+use crate::app::airupd;
+use airup_sdk::{Error, rpc::Response};
+use tokio::task::JoinHandle;
+
+/// Prefix of commands provided by extensions.
+pub const PREFIX: &str = "ext.";
+
+/// Invokes an extension method, in form of `<extension>.<method>`.
+pub fn invoke(method: String, args: Vec<String>) -> JoinHandle<i32> {
+    tokio::spawn(async move {
+        let request = airup_sdk::rpc::Request::new(&method, args);
+        let Ok(response) = airupd().extensions.rpc_invoke(request).await else {
+            return 2;
+        };
+        let error = match response {
+            Response::Ok(_) => return 0,
+            Response::Err(err) => err,
+        };
+
+        tracing::warn!("{PREFIX}{method}: {error}");
+        match error {
+            Error::Exited { exit_code } => exit_code,
+            Error::NotImplemented => 127,
+            Error::InvalidParams { .. } => 1,
+            _ => 2,
+        }
+    })
+}
@@ -0,0 +1,315 @@
+//! Built-in commands that operate the filesystem.
+
+use super::{
+    BuiltinModule, Cwd, Failure, merge_args, parse_timeout, require, spawn, take_flag, take_option,
+};
+use std::{
+    collections::HashMap, hash::BuildHasher, os::unix::fs::PermissionsExt, path::Path,
+    time::Duration,
+};
+use tokio::task::JoinHandle;
+
+/// Interval between checks of `fs.wait_for` and `fs.wait_socket`.
+const WAIT_INTERVAL: Duration = Duration::from_millis(50);
+
+pub fn init<H: BuildHasher>(builtins: &mut HashMap<&'static str, BuiltinModule, H>) {
+    builtins.insert("fs.mkdir", mkdir);
+    builtins.insert("fs.write", write);
+    builtins.insert("fs.append", append);
+    builtins.insert("fs.symlink", symlink);
+    builtins.insert("fs.chmod", chmod);
+    builtins.insert("fs.chown", chown);
+    builtins.insert("fs.remove", remove);
+    builtins.insert("fs.wait_for", wait_for);
+    builtins.insert("fs.wait_socket", wait_socket);
+}
+
+/// `fs.mkdir [-m MODE] [-o OWNER] PATH...`: Creates directories and their parents, like `mkdir -p`. The mode and the owner
+/// are applied to the given directories, but not their parents.
+pub fn mkdir(mut args: Vec<String>, cwd: Cwd) -> JoinHandle<i32> {
+    spawn("fs.mkdir", async move {
+        let mode = take_option(&mut args, "-m")?.map(parse_mode).transpose()?;
+        let owner = take_option(&mut args, "-o")?.map(parse_owner).transpose()?;
+        require(&args, 1)?;
+
+        for path in &args {
+            let resolved = cwd.resolve(path);
+            tokio::fs::create_dir_all(&resolved)
+                .await
+                .map_err(|e| Failure::error(format_args!("failed to create `{path}`: {e}")))?;
+            if let Some(mode) = mode {
+                set_mode(path, &resolved, mode).await?;
+            }
+            if let Some((uid, gid)) = owner {
+                set_owner(path, &resolved, uid, gid)?;
+            }
+        }
+
+        Ok(())
+    })
+}
+
+/// `fs.write PATH TEXT...`: Writes the text to the file, replacing its content.
+pub fn write(args: Vec<String>, cwd: Cwd) -> JoinHandle<i32> {
+    spawn("fs.write", async move {
+        require(&args, 2)?;
+        tokio::fs::write(cwd.resolve(&args[0]), merge_args(&args[1..]))
+            .await
+            .map_err(|e| Failure::error(format_args!("failed to write `{}`: {e}", args[0])))
+    })
+}
+
+/// `fs.append PATH TEXT...`: Appends the text to the file, creating it if it does not exist.
+pub fn append(args: Vec<String>, cwd: Cwd) -> JoinHandle<i32> {
+    spawn("fs.append", async move {
+        use tokio::io::AsyncWriteExt;
+
+        require(&args, 2)?;
+        let error = |e| Failure::error(format_args!("failed to append to `{}`: {e}", args[0]));
+        let mut file = tokio::fs::File::options()
+            .append(true)
+            .create(true)
+            .open(cwd.resolve(&args[0]))
+            .await
+            .map_err(error)?;
+        file.write_all(merge_args(&args[1..]).as_bytes())
+            .await
+            .map_err(error)
+    })
+}
+
+/// `fs.symlink TARGET LINK`: Creates a symbolic link. This succeeds if the link already points to the target. Like
+/// `ln -s`, a relative target is relative to the directory of the link.
+pub fn symlink(args: Vec<String>, cwd: Cwd) -> JoinHandle<i32> {
+    spawn("fs.symlink", async move {
+        require(&args, 2)?;
+        let (target, link) = (&args[0], &args[1]);
+        let resolved = cwd.resolve(link);
+        if tokio::fs::read_link(&resolved)
+            .await
+            .is_ok_and(|x| x == Path::new(target))
+        {
+            return Ok(());
+        }
+        tokio::fs::symlink(target, &resolved)
+            .await
+            .map_err(|e| Failure::error(format_args!("failed to create `{link}`: {e}")))
+    })
+}
+
+/// `fs.chmod MODE PATH...`: Changes permissions of files. The mode is in octal.
+pub fn chmod(mut args: Vec<String>, cwd: Cwd) -> JoinHandle<i32> {
+    spawn("fs.chmod", async move {
+        require(&args, 2)?;
+        let mode = parse_mode(args.remove(0))?;
+        for path in &args {
+            set_mode(path, &cwd.resolve(path), mode).await?;
+        }
+        Ok(())
+    })
+}
+
+/// `fs.chown OWNER PATH...`: Changes owner of files. The owner is in form of `USER[:GROUP]`, where users and groups are
+/// either names or numeric IDs.
+pub fn chown(mut args: Vec<String>, cwd: Cwd) -> JoinHandle<i32> {
+    spawn("fs.chown", async move {
+        require(&args, 2)?;
+        let (uid, gid) = parse_owner(args.remove(0))?;
+        for path in &args {
+            set_owner(path, &cwd.resolve(path), uid, gid)?;
+        }
+        Ok(())
+    })
+}
+
+/// `fs.remove [-r] PATH...`: Removes files or empty directories. With `-r`, directories are removed recursively. Paths that
+/// do not exist are ignored.
+pub fn remove(mut args: Vec<String>, cwd: Cwd) -> JoinHandle<i32> {
+    spawn("fs.remove", async move {
+        let recursive = take_flag(&mut args, "-r");
+        require(&args, 1)?;
+
+        for path in &args {
+            remove_path(&cwd.resolve(path), recursive)
+                .await
+                .map_err(|e| Failure::error(format_args!("failed to remove `{path}`: {e}")))?;
+        }
+
+        Ok(())
+    })
+}
+
+/// Removes a file or a directory, ignoring it if it does not exist.
+async fn remove_path(path: &Path, recursive: bool) -> std::io::Result<()> {
+    let result = match tokio::fs::symlink_metadata(path).await {
+        Ok(x) if x.is_dir() && recursive => tokio::fs::remove_dir_all(path).await,
+        Ok(x) if x.is_dir() => tokio::fs::remove_dir(path).await,
+        Ok(_) => tokio::fs::remove_file(path).await,
+        Err(err) => Err(err),
+    };
+    match result {
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        other => other,
+    }
+}
+
+/// `fs.wait_for [-t TIMEOUT] PATH...`: Waits until all the paths exist. The timeout is in milliseconds.
+pub fn wait_for(mut args: Vec<String>, cwd: Cwd) -> JoinHandle<i32> {
+    spawn("fs.wait_for", async move {
+        let timeout = parse_timeout(take_option(&mut args, "-t")?)?;
+        require(&args, 1)?;
+
+        wait_until(timeout, || async {
+            for path in &args {
+                if !tokio::fs::try_exists(cwd.resolve(path))
+                    .await
+                    .unwrap_or(false)
+                {
+                    return false;
+                }
+            }
+            true
+        })
+        .await
+    })
+}
+
+/// `fs.wait_socket [-t TIMEOUT] PATH...`: Waits until all the Unix sockets accept connections. The timeout is in
+/// milliseconds.
+pub fn wait_socket(mut args: Vec<String>, cwd: Cwd) -> JoinHandle<i32> {
+    spawn("fs.wait_socket", async move {
+        let timeout = parse_timeout(take_option(&mut args, "-t")?)?;
+        require(&args, 1)?;
+
+        wait_until(timeout, || async {
+            for path in &args {
+                if tokio::net::UnixStream::connect(cwd.resolve(path))
+                    .await
+                    .is_err()
+                {
+                    return false;
+                }
+            }
+            true
+        })
+        .await
+    })
+}
+
+/// Polls the condition until it is satisfied or the timeout expired.
+async fn wait_until<F, Fut>(timeout: Option<Duration>, condition: F) -> Result<(), Failure>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = bool>,
+{
+    let countdown = airupfx::time::countdown(timeout);
+    while !condition().await {
+        if countdown.left().is_some_and(|x| x.is_zero()) {
+            return Err(Failure::error("timed out"));
+        }
+        tokio::time::sleep(WAIT_INTERVAL).await;
+    }
+    Ok(())
+}
+
+fn parse_mode(s: String) -> Result<u32, Failure> {
+    u32::from_str_radix(&s, 8).map_err(|_| Failure::usage(format_args!("invalid mode `{s}`")))
+}
+
+/// Parses an owner in form of `USER[:GROUP]`.
+fn parse_owner(s: String) -> Result<(Option<u32>, Option<u32>), Failure> {
+    let (user, group) = match s.split_once(':') {
+        Some((user, group)) => (user, Some(group)),
+        None => (s.as_str(), None),
+    };
+    let uid = match user {
+        "" => None,
+        x => Some(
+            x.parse()
+                .ok()
+                .or_else(|| airupfx::env::with_user_by_name(x, |x| **x.id()))
+                .ok_or_else(|| Failure::usage(format_args!("user `{x}` not found")))?,
+        ),
+    };
+    let gid = match group {
+        None | Some("") => None,
+        Some(x) => Some(
+            x.parse()
+                .ok()
+                .or_else(|| airupfx::env::with_group_by_name(x, |x| **x.id()))
+                .ok_or_else(|| Failure::usage(format_args!("group `{x}` not found")))?,
+        ),
+    };
+
+    Ok((uid, gid))
+}
+
+async fn set_mode(path: &str, resolved: &Path, mode: u32) -> Result<(), Failure> {
+    tokio::fs::set_permissions(resolved, std::fs::Permissions::from_mode(mode))
+        .await
+        .map_err(|e| Failure::error(format_args!("failed to change mode of `{path}`: {e}")))
+}
+
+fn set_owner(
+    path: &str,
+    resolved: &Path,
+    uid: Option<u32>,
+    gid: Option<u32>,
+) -> Result<(), Failure> {
+    std::os::unix::fs::chown(resolved, uid, gid)
+        .map_err(|e| Failure::error(format_args!("failed to change owner of `{path}`: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_mode() {
+        assert_eq!(super::parse_mode("755".into()).unwrap(), 0o755);
+        assert_eq!(super::parse_mode("0600".into()).unwrap(), 0o600);
+        super::parse_mode("rwx".into()).unwrap_err();
+        super::parse_mode("888".into()).unwrap_err();
+    }
+
+    #[test]
+    fn parse_owner() {
+        assert_eq!(super::parse_owner("0".into()).unwrap(), (Some(0), None));
+        assert_eq!(
+            super::parse_owner("0:0".into()).unwrap(),
+            (Some(0), Some(0))
+        );
+        assert_eq!(super::parse_owner(":5".into()).unwrap(), (None, Some(5)));
+        assert_eq!(
+            super::parse_owner("1000:".into()).unwrap(),
+            (Some(1000), None)
+        );
+        super::parse_owner("__airup_no_such_user__".into()).unwrap_err();
+        super::parse_owner("0:__airup_no_such_group__".into()).unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn remove() {
+        let dir = std::env::temp_dir().join(format!("airup-test-fs-remove-{}", std::process::id()));
+        tokio::fs::create_dir_all(dir.join("a/b")).await.unwrap();
+        tokio::fs::write(dir.join("file"), "").await.unwrap();
+
+        // Paths that do not exist are ignored
+        remove_path(&dir.join("nonexistent"), false).await.unwrap();
+        remove_path(&dir.join("file"), false).await.unwrap();
+        assert!(!dir.join("file").exists());
+
+        // Non-empty directories are removed only if recursive
+        remove_path(&dir.join("a"), false).await.unwrap_err();
+        remove_path(&dir, true).await.unwrap();
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn cwd() {
+        let cwd = Cwd::new(Some("/var/lib/x".into()));
+        assert_eq!(cwd.resolve("data"), Path::new("/var/lib/x/data"));
+        assert_eq!(cwd.resolve("/etc/hosts"), Path::new("/etc/hosts"));
+        assert_eq!(Cwd::default().resolve("data"), Path::new("data"));
+    }
+}
@@ -0,0 +1,229 @@
+//! Built-in commands of ACE.
+//!
+//! Built-in commands exit with code `1` if they were called with invalid arguments, or `2` if the operation failed.
+
+pub mod extension;
+mod fs;
+mod process;
+mod system;
+
+use airupfx::process::ExitStatus;
+use libc::SIGTERM;
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    hash::BuildHasher,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tokio::task::JoinHandle;
+
+pub type BuiltinModule = fn(args: Vec<String>, cwd: Cwd) -> JoinHandle<i32>;
+
+/// Working directory of a built-in command, which relative paths in its arguments are resolved against.
+#[derive(Debug, Clone, Default)]
+pub struct Cwd(Option<PathBuf>);
+impl Cwd {
+    /// Creates a new [`Cwd`] instance. If `dir` is `None`, paths are resolved against working directory of the daemon.
+    pub fn new(dir: Option<PathBuf>) -> Self {
+        Self(dir)
+    }
+
+    /// Resolves a path against the working directory.
+    fn resolve(&self, path: impl AsRef<Path>) -> PathBuf {
+        match &self.0 {
+            Some(dir) => dir.join(path),
+            None => path.as_ref().into(),
+        }
+    }
+}
+
+pub fn init<H: BuildHasher>(builtins: &mut HashMap<&'static str, BuiltinModule, H>) {
+    builtins.insert("noop", noop);
+    builtins.insert("console.setup", console_setup);
+    builtins.insert("console.info", console_info);
+    builtins.insert("console.warn", console_warn);
+    builtins.insert("console.error", console_error);
+    builtins.insert("builtin.sleep", sleep);
+    fs::init(builtins);
+    process::init(builtins);
+    system::init(builtins);
+}
+
+pub fn console_setup(args: Vec<String>, _: Cwd) -> JoinHandle<i32> {
+    tokio::spawn(async move {
+        let path = match args.first() {
+            Some(x) => x,
+            None => return 1,
+        };
+        match airupfx::env::setup_stdio(path.as_ref()).await {
+            Ok(()) => 0,
+            Err(_) => 2,
+        }
+    })
+}
+
+pub fn console_info(args: Vec<String>, _: Cwd) -> JoinHandle<i32> {
+    tracing::info!(target: "console", "{}", merge_args(&args));
+    tokio::spawn(async { 0 })
+}
+
+pub fn console_warn(args: Vec<String>, _: Cwd) -> JoinHandle<i32> {
+    tracing::warn!(target: "console", "{}", merge_args(&args));
+    tokio::spawn(async { 0 })
+}
+
+pub fn console_error(args: Vec<String>, _: Cwd) -> JoinHandle<i32> {
+    tracing::error!(target: "console", "{}", merge_args(&args));
+    tokio::spawn(async { 0 })
+}
+
+pub fn noop(_: Vec<String>, _: Cwd) -> JoinHandle<i32> {
+    tokio::spawn(async { 0 })
+}
+
+pub fn sleep(args: Vec<String>, _: Cwd) -> JoinHandle<i32> {
+    tokio::spawn(async move {
+        let duration = match args.first() {
+            Some(x) => x,
+            None => return 1,
+        };
+        let duration: u64 = match duration.parse() {
+            Ok(x) => x,
+            Err(_) => return 2,
+        };
+
+        tokio::time::sleep(Duration::from_millis(duration)).await;
+
+        0
+    })
+}
+
+pub async fn wait(rx: &mut JoinHandle<i32>) -> ExitStatus {
+    (rx.await).map_or(ExitStatus::Signaled(SIGTERM), |code| {
+        ExitStatus::Exited(code as _)
+    })
+}
+
+/// A failure of a built-in command.
+#[derive(Debug)]
+enum Failure {
+    /// The command was called with invalid arguments.
+    Usage(String),
+
+    /// The operation failed.
+    Error(String),
+}
+impl Failure {
+    fn usage(message: impl Display) -> Self {
+        Self::Usage(message.to_string())
+    }
+
+    fn error(message: impl Display) -> Self {
+        Self::Error(message.to_string())
+    }
+}
+
+/// Spawns a built-in command, which logs its failure and exits with the corresponding code.
+fn spawn<F>(name: &'static str, f: F) -> JoinHandle<i32>
+where
+    F: Future<Output = Result<(), Failure>> + Send + 'static,
+{
+    tokio::spawn(async move {
+        match f.await {
+            Ok(()) => 0,
+            Err(Failure::Usage(message)) => {
+                tracing::warn!("{name}: {message}");
+                1
+            }
+            Err(Failure::Error(message)) => {
+                tracing::warn!("{name}: {message}");
+                2
+            }
+        }
+    })
+}
+
+/// Returns a usage error if there are less arguments than required.
+fn require(args: &[String], count: usize) -> Result<(), Failure> {
+    match args.len() < count {
+        true => Err(Failure::usage("too few arguments")),
+        false => Ok(()),
+    }
+}
+
+/// Removes an option from the arguments, returning its value.
+fn take_option(args: &mut Vec<String>, name: &str) -> Result<Option<String>, Failure> {
+    let Some(index) = args.iter().position(|x| x == name) else {
+        return Ok(None);
+    };
+    if index + 1 >= args.len() {
+        return Err(Failure::usage(format_args!(
+            "option `{name}` requires a value"
+        )));
+    }
+    let value = args.remove(index + 1);
+    args.remove(index);
+    Ok(Some(value))
+}
+
+/// Removes a flag from the arguments, returning `true` if it was present.
+fn take_flag(args: &mut Vec<String>, name: &str) -> bool {
+    let len = args.len();
+    args.retain(|x| x != name);
+    args.len() != len
+}
+
+/// Parses a timeout in milliseconds.
+fn parse_timeout(value: Option<String>) -> Result<Option<Duration>, Failure> {
+    value
+        .map(|x| {
+            x.parse()
+                .map(Duration::from_millis)
+                .map_err(|_| Failure::usage(format_args!("invalid timeout `{x}`")))
+        })
+        .transpose()
+}
+
+fn merge_args(args: &[String]) -> String {
+    let mut result = String::with_capacity(args.len() * 12);
+    for arg in args {
+        result.push_str(arg);
+        result.push(' ');
+    }
+    result.pop();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    fn args(s: &str) -> Vec<String> {
+        s.split_whitespace().map(Into::into).collect()
+    }
+
+    #[test]
+    fn take_option() {
+        let mut x = args("-m 755 a -o root b");
+        assert_eq!(
+            super::take_option(&mut x, "-m").unwrap().as_deref(),
+            Some("755")
+        );
+        assert_eq!(
+            super::take_option(&mut x, "-o").unwrap().as_deref(),
+            Some("root")
+        );
+        assert_eq!(super::take_option(&mut x, "-t").unwrap(), None);
+        assert_eq!(x, args("a b"));
+
+        let mut x = args("a -m");
+        super::take_option(&mut x, "-m").unwrap_err();
+    }
+
+    #[test]
+    fn take_flag() {
+        let mut x = args("-r a -r b");
+        assert!(super::take_flag(&mut x, "-r"));
+        assert!(!super::take_flag(&mut x, "-r"));
+        assert_eq!(x, args("a b"));
+    }
+}
@@ -0,0 +1,38 @@
+//! Built-in commands that operate processes.
+
+use super::{BuiltinModule, Cwd, Failure, require, spawn};
+use std::{collections::HashMap, hash::BuildHasher};
+use tokio::task::JoinHandle;
+
+pub fn init<H: BuildHasher>(builtins: &mut HashMap<&'static str, BuiltinModule, H>) {
+    builtins.insert("process.signal", signal);
+}
+
+/// `process.signal SIGNAL TARGET...`: Sends a signal to processes. Each target is either a PID, or path to a PID file.
+pub fn signal(mut args: Vec<String>, cwd: Cwd) -> JoinHandle<i32> {
+    spawn("process.signal", async move {
+        require(&args, 2)?;
+        let signal = args.remove(0);
+        let signum = airup_sdk::signal::parse(&signal)
+            .ok_or_else(|| Failure::usage(format_args!("unknown signal `{signal}`")))?;
+
+        for target in &args {
+            let pid = match target.parse::<i64>() {
+                Ok(x) => x,
+                Err(_) => tokio::fs::read_to_string(cwd.resolve(target))
+                    .await
+                    .map_err(|e| Failure::error(format_args!("failed to read `{target}`: {e}")))?
+                    .trim()
+                    .parse()
+                    .map_err(|_| Failure::error(format_args!("invalid PID file `{target}`")))?,
+            };
+            if pid <= 0 {
+                return Err(Failure::usage(format_args!("invalid PID `{pid}`")));
+            }
+            airupfx::process::kill(pid, signum)
+                .map_err(|e| Failure::error(format_args!("failed to signal {pid}: {e}")))?;
+        }
+
+        Ok(())
+    })
+}
@@ -0,0 +1,87 @@
+//! Built-in commands that configure the system.
+
+use super::{BuiltinModule, Cwd, Failure, require, spawn, take_option};
+use std::{collections::HashMap, hash::BuildHasher};
+use tokio::task::JoinHandle;
+
+pub fn init<H: BuildHasher>(builtins: &mut HashMap<&'static str, BuiltinModule, H>) {
+    builtins.insert("system.hostname", hostname);
+    builtins.insert("system.sysctl", sysctl);
+}
+
+/// `system.hostname NAME` or `system.hostname -f FILE`: Sets hostname of the system. If a file is given, its first line is
+/// used as the hostname.
+pub fn hostname(mut args: Vec<String>, cwd: Cwd) -> JoinHandle<i32> {
+    spawn("system.hostname", async move {
+        let name = match take_option(&mut args, "-f")? {
+            Some(path) => tokio::fs::read_to_string(cwd.resolve(&path))
+                .await
+                .map_err(|e| Failure::error(format_args!("failed to read `{path}`: {e}")))?
+                .lines()
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_owned(),
+            None => args
+                .first()
+                .cloned()
+                .ok_or_else(|| Failure::usage("too few arguments"))?,
+        };
+        if name.is_empty() {
+            return Err(Failure::usage("hostname must not be empty"));
+        }
+
+        let result = unsafe { libc::sethostname(name.as_ptr().cast(), name.len() as _) };
+        match result {
+            0 => Ok(()),
+            _ => Err(Failure::error(format_args!(
+                "failed to set hostname: {}",
+                std::io::Error::last_os_error()
+            ))),
+        }
+    })
+}
+
+/// `system.sysctl KEY=VALUE...`: Writes kernel parameters, where keys are in form of `net.ipv4.ip_forward`.
+pub fn sysctl(args: Vec<String>, _: Cwd) -> JoinHandle<i32> {
+    spawn("system.sysctl", async move {
+        require(&args, 1)?;
+
+        for arg in &args {
+            let (key, value) = parse_sysctl(arg)?;
+            tokio::fs::write(sysctl_path(key), value)
+                .await
+                .map_err(|e| Failure::error(format_args!("failed to write `{key}`: {e}")))?;
+        }
+
+        Ok(())
+    })
+}
+
+/// Parses a `KEY=VALUE` argument of `system.sysctl`.
+fn parse_sysctl(arg: &str) -> Result<(&str, &str), Failure> {
+    let (key, value) = arg
+        .split_once('=')
+        .ok_or_else(|| Failure::usage(format_args!("expected `KEY=VALUE`, found `{arg}`")))?;
+    Ok((key.trim(), value.trim()))
+}
+
+/// Returns path of a kernel parameter in `/proc/sys`.
+fn sysctl_path(key: &str) -> String {
+    format!("/proc/sys/{}", key.replace('.', "/"))
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn sysctl() {
+        let (key, value) = super::parse_sysctl(" net.ipv4.ip_forward = 1 ").unwrap();
+        assert_eq!((key, value), ("net.ipv4.ip_forward", "1"));
+        assert_eq!(super::sysctl_path(key), "/proc/sys/net/ipv4/ip_forward");
+        assert_eq!(
+            super::parse_sysctl("kernel.x=a=b").unwrap(),
+            ("kernel.x", "a=b")
+        );
+        super::parse_sysctl("net.ipv4.ip_forward").unwrap_err();
+    }
+}
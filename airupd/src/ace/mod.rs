@@ -1,24 +1,81 @@
 //! The Airup Command Engine.
 //!
 //! The **Airup Command Engine** (shortly, "ACE") is a simplified command language for Airup, with a syntax like (but
-//! different from) POSIX shells. It can spawn single commands, or execute built-in commands.
+//! different from) POSIX shells. It can spawn commands, execute built-in commands or invoke methods of extensions (as
+//! `ext.<extension>.<method>`), connect them with pipelines (`|`), redirect their standard I/O to files (`<`, `>`, `>>`,
+//! `2>`, `2>>`, `&>`, `&>>` and `2>&1`) and join pipelines with `&&`, `||` and `;`.
+//!
+//! Unlike POSIX shells, `|`, `&&` and `||` are only recognized as standalone words, and redirections are only recognized at
+//! the beginning of a word, so arguments like `http://host/?a=1&b=2` do not need quoting.
+//!
+//! Commands can be prefixed by modifiers, which compose with each other, e.g. `@nofail @timeout 5000 cmd`:
+//!  - `@nofail`: The command always succeeds.
+//!  - `@async`: The command succeeds immediately, without waiting for it to complete.
+//!  - `@timeout MILLIS`: The command is stopped and fails if it did not complete in time.
+//!  - `@retry COUNT DELAY`: If the command failed, it is retried at most `COUNT` times, after `DELAY` milliseconds.
+//!  - `@user USER`: The command runs as the user, which is either a username or a UID.
+//!  - `@cwd DIR`: The command runs in the directory.
+//!  - `@env KEY=VALUE`: The command runs with the environment variable set.
+//!
+//! Built-in commands and extension methods run inside the daemon, so `@user` and `@env` cannot be applied to them. Their
+//! relative paths are resolved against the working directory. Redirections are opened as the user, and in the working
+//! directory, that the command finally runs with.
+//!
+//! Variables (`$VAR`, `${VAR}`, `${VAR:-default}` and `${VAR:?message}`) are expanded in both quoted and unquoted words,
+//! against the environment that the command runs in. Expanded values are never split into multiple words.
 
 mod builtins;
-mod parser;
 
-use airup_sdk::error::IntoApiError;
-use airupfx::isolator::Realm;
-use airupfx::process::{CommandEnv, ExitStatus, Wait, WaitError};
-use libc::SIGTERM;
-use std::{collections::HashMap, sync::Arc, time::Duration};
-use tokio::task::JoinHandle;
+use airup_sdk::{ace as parser, error::IntoApiError};
+use airupfx::process::{CommandEnv, ExitStatus, Stdio, Wait, WaitError};
+use airupfx::{isolator::Realm, prelude::*};
+use libc::{SIGKILL, SIGTERM};
+use std::{
+    collections::HashMap,
+    ffi::{CStr, CString},
+    os::{
+        fd::{AsRawFd, FromRawFd, OwnedFd, RawFd},
+        unix::{ffi::OsStrExt, net::UnixStream},
+    },
+    path::Path,
+    sync::{
+        Arc, Mutex,
+        atomic::{self, AtomicBool},
+    },
+    time::Duration,
+};
+use tokio::{sync::watch, task::JoinHandle};
+
+/// Time to wait for a command to exit after the kill signal is sent, if it is not `SIGKILL`. After that, `SIGKILL` is sent.
+pub const KILL_SIGNAL_TIMEOUT: Duration = Duration::from_secs(5);
 
 /// The Airup Command Engine.
-#[derive(Default)]
+#[derive(Clone)]
 pub struct Ace {
     pub env: CommandEnv,
     pub realm: Option<Arc<Realm>>,
     pub modules: Modules,
+
+    /// Signal sent to commands that timed out.
+    pub stop_signal: i32,
+
+    /// Signal sent to commands that timed out and did not exit in the grace period after receiving the stop signal.
+    pub kill_signal: i32,
+
+    /// Grace period between sending the stop signal and the kill signal. If this is `None`, the command's timeout is used.
+    pub grace_period: Option<Duration>,
+}
+impl Default for Ace {
+    fn default() -> Self {
+        Self {
+            env: CommandEnv::default(),
+            realm: None,
+            modules: Modules::default(),
+            stop_signal: SIGTERM,
+            kill_signal: SIGKILL,
+            grace_period: None,
+        }
+    }
 }
 impl Ace {
     /// Creates a new [`Ace`] instance with default settings.
@@ -27,9 +84,35 @@ impl Ace {
     }
 
     /// Runs the given command, returning the child.
+    ///
+    /// If the command is a list of pipelines joined by `&&`, `||` or `;`, the first pipeline is spawned immediately, and the
+    /// rest are run in background. The returned child represents the whole list.
     pub async fn run(&self, cmd: &str) -> Result<Child, Error> {
-        self.run_parsed(parser::Command::parse(cmd).map_err(|x| Error::Parse(x.to_string()))?)
-            .await
+        let script = parser::Script::parse(cmd).map_err(|x| Error::Parse(x.to_string()))?;
+        let first = self.run_pipeline(script.first).await?;
+        if script.rest.is_empty() {
+            return Ok(first);
+        }
+
+        let ace = self.clone();
+        Ok(Child::Compound(Compound::start(
+            first,
+            |compound| async move { ace.run_list(&compound, script.rest).await },
+        )))
+    }
+
+    /// Parses and expands the given command without running it. Returns argument vectors of all simple commands in the
+    /// command, in order they appear.
+    pub fn expand(&self, cmd: &str) -> Result<Vec<Vec<String>>, Error> {
+        let script = parser::Script::parse(cmd).map_err(|x| Error::Parse(x.to_string()))?;
+        let lookup = |name: &str| lookup_var(&self.env, name);
+        script
+            .commands()
+            .map(|simple| {
+                let cmd = simple.expand(&lookup).map_err(Error::Expand)?;
+                Ok(std::iter::once(cmd.module).chain(cmd.args).collect())
+            })
+            .collect()
     }
 
     /// Runs the given command and waits until it completed.
@@ -48,7 +131,13 @@ impl Ace {
             Ok(wait) => Ok(CommandExitError::from_wait(&wait)),
             Err(err) => match err {
                 Error::TimedOut => {
-                    child.kill_timeout(SIGTERM, timeout).await?;
+                    child
+                        .kill_timeout(
+                            self.stop_signal,
+                            self.kill_signal,
+                            self.grace_period.or(timeout),
+                        )
+                        .await?;
                     Err(Error::TimedOut)
                 }
                 other => Err(other),
@@ -56,41 +145,257 @@ impl Ace {
         }
     }
 
-    async fn run_parsed(&self, cmd: parser::Command) -> Result<Child, Error> {
-        if cmd.module == "@nofail" {
-            let otherwise =
-                |_| Child::AlwaysSuccess(Box::new(Child::Builtin(builtins::noop(vec![]).into())));
-            let Some(wrapped) = cmd.wrap(std::convert::identity) else {
-                // Odd though using `Error::TimedOut` here it seemed, `otherwise` does not actually uses its input argument,
-                // so anything can be filled here. `Error::TimedOut` is the only variant that requires no fields.
-                return Ok(otherwise(Error::TimedOut));
-            };
-            return Ok(Child::AlwaysSuccess(Box::new(
-                Box::pin(self.run_parsed(wrapped))
-                    .await
-                    .unwrap_or_else(otherwise),
-            )));
+    /// Spawns the given program directly, without parsing it as an ACE command.
+    pub async fn spawn(&self, program: &str, args: &[String]) -> Result<Child, Error> {
+        self.run_bin_command(
+            &parser::Command {
+                module: program.into(),
+                args: args.to_vec(),
+            },
+            &self.env,
+        )
+        .await
+    }
+
+    /// Runs rest pipelines of a list, after the first one. The list stops if a pipeline was terminated by a signal.
+    async fn run_list(
+        &self,
+        compound: &Compound,
+        rest: Vec<(parser::Operator, parser::Pipeline)>,
+    ) -> Result<Wait, Error> {
+        let mut wait = compound.current().wait().await?;
+        for (operator, pipeline) in rest {
+            if wait.signal().is_some() || compound.is_aborted() {
+                break;
+            }
+            if !operator.should_run(wait.is_success()) {
+                continue;
+            }
+
+            let child = compound
+                .set_current(self.run_pipeline(pipeline).await?)
+                .await?;
+            wait = child.wait().await?;
         }
-        if cmd.module == "@async" {
-            let wrapped = cmd
-                .wrap(std::convert::identity)
-                .ok_or_else(|| Error::Parse("'@async' requires a following command".into()))?;
-            return Ok(Child::Async(Box::new(
-                Box::pin(self.run_parsed(wrapped)).await?,
-            )));
+
+        Ok(wait)
+    }
+
+    async fn run_pipeline(&self, pipeline: parser::Pipeline) -> Result<Child, Error> {
+        let mut commands = pipeline.0;
+        if commands.len() == 1 {
+            return self
+                .run_simple(commands.pop().unwrap(), self.env.clone())
+                .await;
         }
-        if let Some(&builtin) = self.modules.builtins.get(&cmd.module[..]) {
-            return Ok(Child::Builtin(builtin(cmd.args).into()));
+
+        let count = commands.len();
+        let mut children = Vec::with_capacity(count);
+        let mut stdin: Option<OwnedFd> = None;
+        for (index, command) in commands.into_iter().enumerate() {
+            let mut env = self.env.clone();
+            if let Some(reader) = stdin.take() {
+                env.stdin(Stdio::Fd(Arc::new(reader)));
+            }
+            if index + 1 < count {
+                let (reader, writer) = std::io::pipe()?;
+                env.stdout(Stdio::Fd(Arc::new(writer.into())));
+                stdin = Some(reader.into());
+            }
+
+            match self.run_simple(command, env).await {
+                Ok(child) => children.push(child),
+                Err(err) => {
+                    for child in &children {
+                        _ = child.kill().await;
+                    }
+                    return Err(err);
+                }
+            }
         }
-        self.run_bin_command(&cmd).await
+
+        Ok(Child::Pipeline(children))
     }
 
-    async fn run_bin_command(&self, cmd: &parser::Command) -> Result<Child, Error> {
+    async fn run_simple(
+        &self,
+        simple: parser::Simple,
+        mut env: CommandEnv,
+    ) -> Result<Child, Error> {
+        let lookup = |name: &str| lookup_var(&self.env, name);
+        let expand = |expr: &parser::Expr| expr.expand(&lookup).map_err(Error::Expand);
+        let command = simple.expand(&lookup).map_err(Error::Expand)?;
+        let target = target_env(&command, &env)?;
+
+        for redirect in &simple.redirects {
+            match redirect {
+                parser::Redirect::Stdin(path) => {
+                    env.stdin(open_redirect(&target, &expand(path)?, None).await?);
+                }
+                parser::Redirect::Stdout { path, append } => {
+                    env.stdout(open_redirect(&target, &expand(path)?, Some(*append)).await?);
+                }
+                parser::Redirect::Stderr { path, append } => {
+                    env.stderr(open_redirect(&target, &expand(path)?, Some(*append)).await?);
+                }
+                parser::Redirect::Both { path, append } => {
+                    let file = open_redirect(&target, &expand(path)?, Some(*append)).await?;
+                    env.stdout(file.clone()).stderr(file);
+                }
+                parser::Redirect::StderrToStdout => {
+                    let stdout = env.stdout.clone();
+                    env.stderr(stdout);
+                }
+            }
+        }
+
+        self.run_parsed(command, &env).await
+    }
+
+    fn run_parsed<'a>(
+        &'a self,
+        cmd: parser::Command,
+        env: &'a CommandEnv,
+    ) -> BoxFuture<'a, Result<Child, Error>> {
+        Box::pin(async move {
+            if cmd.module == "@nofail" {
+                let otherwise = |_| {
+                    Child::AlwaysSuccess(Box::new(Child::Builtin(
+                        builtins::noop(vec![], Default::default()).into(),
+                    )))
+                };
+                let Some(wrapped) = cmd.wrap(std::convert::identity) else {
+                    // Odd though using `Error::TimedOut` here it seemed, `otherwise` does not actually uses its input
+                    // argument, so anything can be filled here. `Error::TimedOut` is the only variant that requires no
+                    // fields.
+                    return Ok(otherwise(Error::TimedOut));
+                };
+                return Ok(Child::AlwaysSuccess(Box::new(
+                    self.run_parsed(wrapped, env)
+                        .await
+                        .unwrap_or_else(otherwise),
+                )));
+            }
+            if cmd.module == "@async" {
+                let wrapped = cmd
+                    .wrap(std::convert::identity)
+                    .ok_or_else(|| Error::Parse("'@async' requires a following command".into()))?;
+                return Ok(Child::Async(Box::new(self.run_parsed(wrapped, env).await?)));
+            }
+            if cmd.module == "@timeout" {
+                let ([timeout], wrapped) = modifier(cmd, "a timeout and a following command")?;
+                let timeout = parse_millis(&timeout)?;
+                let child = self.run_parsed(wrapped, env).await?;
+                let (stop_signal, kill_signal) = (self.stop_signal, self.kill_signal);
+                let grace_period = self.grace_period.unwrap_or(timeout);
+                return Ok(Child::Compound(Compound::start(
+                    child,
+                    |compound| async move {
+                        let child = compound.current();
+                        match child.wait_timeout(Some(timeout)).await {
+                            Err(Error::TimedOut) => {
+                                child
+                                    .kill_timeout(stop_signal, kill_signal, Some(grace_period))
+                                    .await?;
+                                Err(Error::TimedOut)
+                            }
+                            other => other,
+                        }
+                    },
+                )));
+            }
+            if cmd.module == "@retry" {
+                let ([count, delay], wrapped) =
+                    modifier(cmd, "a retry count, a delay and a following command")?;
+                let count: u32 = count
+                    .parse()
+                    .map_err(|_| Error::Parse(format!("invalid retry count `{count}`")))?;
+                let delay = parse_millis(&delay)?;
+                let child = self.run_parsed(wrapped.clone(), env).await?;
+                let (ace, env) = (self.clone(), env.clone());
+                return Ok(Child::Compound(Compound::start(
+                    child,
+                    |compound| async move {
+                        let mut result = compound.current().wait().await;
+                        for _ in 0..count {
+                            if result.as_ref().is_ok_and(Wait::is_success) || compound.is_aborted()
+                            {
+                                break;
+                            }
+                            tokio::time::sleep(delay).await;
+                            let child = ace.run_parsed(wrapped.clone(), &env).await?;
+                            result = compound.set_current(child).await?.wait().await;
+                        }
+                        result
+                    },
+                )));
+            }
+            if cmd.module == "@user" {
+                let ([user], wrapped) = modifier(cmd, "a user and a following command")?;
+                self.reject_builtin("@user", &wrapped)?;
+                let mut env = env.clone();
+                set_user(&mut env, &user)?;
+                return self.run_parsed(wrapped, &env).await;
+            }
+            if cmd.module == "@cwd" {
+                let ([dir], wrapped) = modifier(cmd, "a directory and a following command")?;
+                let mut env = env.clone();
+                let dir = match &env.working_dir {
+                    Some(x) => x.join(dir),
+                    None => dir.into(),
+                };
+                env.working_dir(dir);
+                return self.run_parsed(wrapped, &env).await;
+            }
+            if cmd.module == "@env" {
+                let ([var], wrapped) = modifier(cmd, "a `KEY=VALUE` pair and a following command")?;
+                self.reject_builtin("@env", &wrapped)?;
+                let (key, value) = var
+                    .split_once('=')
+                    .ok_or_else(|| Error::Parse(format!("expected `KEY=VALUE`, found `{var}`")))?;
+                let mut env = env.clone();
+                env.var(key, value);
+                return self.run_parsed(wrapped, &env).await;
+            }
+            if let Some(method) = cmd.module.strip_prefix(builtins::extension::PREFIX) {
+                return Ok(Child::Builtin(
+                    builtins::extension::invoke(method.into(), cmd.args).into(),
+                ));
+            }
+            if let Some(&builtin) = self.modules.builtins.get(&cmd.module[..]) {
+                let cwd = builtins::Cwd::new(env.working_dir.clone());
+                return Ok(Child::Builtin(builtin(cmd.args, cwd).into()));
+            }
+            self.run_bin_command(&cmd, env).await
+        })
+    }
+
+    /// Fails if the command that a modifier is finally applied to is a built-in command or an extension method.
+    fn reject_builtin(&self, modifier: &str, cmd: &parser::Command) -> Result<(), Error> {
+        let (_, module) = modifier_chain(cmd);
+        match module {
+            Some(x)
+                if x.starts_with(builtins::extension::PREFIX)
+                    || self.modules.builtins.contains_key(x) =>
+            {
+                Err(Error::Parse(format!(
+                    "'{modifier}' cannot be applied to built-in command `{x}`"
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    async fn run_bin_command(
+        &self,
+        cmd: &parser::Command,
+        env: &CommandEnv,
+    ) -> Result<Child, Error> {
         let mut command = airupfx::process::Command::new(&cmd.module);
         cmd.args.iter().for_each(|x| {
             command.arg(x);
         });
-        command.env = self.env.clone();
+        command.env = env.clone();
         let child = command.spawn().await?;
         if let Some(realm) = &self.realm {
             realm.add(child.id())?;
@@ -99,6 +404,273 @@ impl Ace {
     }
 }
 
+/// Modifiers and numbers of their parameters.
+const MODIFIERS: &[(&str, usize)] = &[
+    ("@nofail", 0),
+    ("@async", 0),
+    ("@timeout", 1),
+    ("@retry", 2),
+    ("@user", 1),
+    ("@cwd", 1),
+    ("@env", 1),
+];
+
+/// Returns modifiers applied to the command with their parameters, in order they are applied, and the module that is
+/// finally run. The module is `None` if a modifier lacks a following command.
+fn modifier_chain(cmd: &parser::Command) -> (Vec<(&str, &[String])>, Option<&str>) {
+    let mut chain = Vec::new();
+    let mut module = &cmd.module;
+    let mut rest = &cmd.args[..];
+    while let Some(&(name, count)) = MODIFIERS.iter().find(|(name, _)| name == module) {
+        if rest.len() <= count {
+            return (chain, None);
+        }
+        chain.push((name, &rest[..count]));
+        module = &rest[count];
+        rest = &rest[count + 1..];
+    }
+    (chain, Some(module))
+}
+
+/// Returns the environment that the command finally runs with, applying its `@user` and `@cwd` modifiers.
+fn target_env(cmd: &parser::Command, env: &CommandEnv) -> Result<CommandEnv, Error> {
+    let mut env = env.clone();
+    for (name, params) in modifier_chain(cmd).0 {
+        match name {
+            "@user" => set_user(&mut env, &params[0])?,
+            "@cwd" => {
+                let dir = match &env.working_dir {
+                    Some(x) => x.join(&params[0]),
+                    None => (&params[0]).into(),
+                };
+                env.working_dir(dir);
+            }
+            _ => {}
+        }
+    }
+    Ok(env)
+}
+
+/// Splits parameters of a modifier from the command that it modifies.
+fn modifier<const N: usize>(
+    cmd: parser::Command,
+    requirement: &str,
+) -> Result<([String; N], parser::Command), Error> {
+    let name = cmd.module.clone();
+    cmd.wrap_params()
+        .ok_or_else(|| Error::Parse(format!("'{name}' requires {requirement}")))
+}
+
+fn parse_millis(s: &str) -> Result<Duration, Error> {
+    s.parse()
+        .map(Duration::from_millis)
+        .map_err(|_| Error::Parse(format!("invalid duration `{s}`, expected milliseconds")))
+}
+
+/// Makes the command run as the given user, which is either a username or a UID.
+///
+/// The user must exist in the user database even if it is given by UID, since its group cannot be determined otherwise.
+fn set_user(env: &mut CommandEnv, user: &str) -> Result<(), Error> {
+    let Ok(uid) = user.parse::<u32>() else {
+        env.login(user)?;
+        return Ok(());
+    };
+    let name = (uid as usize)
+        .try_into()
+        .ok()
+        .and_then(|x| airupfx::env::with_user_by_id(&x, |x| x.name().to_owned()))
+        .ok_or_else(|| Error::Io(format!("user with UID {uid} not found")))?;
+    env.login(&name[..])?;
+    Ok(())
+}
+
+/// Looks a variable up in the environment that a command would run in.
+fn lookup_var(env: &CommandEnv, name: &str) -> Option<String> {
+    let value = match env.vars.iter().rev().find(|(k, _)| k == name) {
+        Some((_, value)) => value.clone(),
+        None if env.clear_vars => None,
+        None => std::env::var_os(name),
+    };
+    value.map(|x| x.to_string_lossy().into_owned())
+}
+
+/// Opens the file of a redirection. Relative paths are resolved against the working directory of the command, and the file
+/// is opened as the user that the command runs as.
+///
+/// If `append` is `None`, the file is opened for reading. Otherwise, it is opened for writing, and created if it does not
+/// exist.
+async fn open_redirect(env: &CommandEnv, path: &str, append: Option<bool>) -> Result<Stdio, Error> {
+    let path = match &env.working_dir {
+        Some(dir) => dir.join(path),
+        None => Path::new(path).into(),
+    };
+    let file = match env.uid {
+        Some(uid) if uid != unsafe { libc::geteuid() } => {
+            let gid = env.gid.unwrap_or_else(|| unsafe { libc::getegid() });
+            let groups = env.groups.clone().unwrap_or_default();
+            open_as(&path, append, uid, gid, groups).await
+        }
+        _ => {
+            let mut options = std::fs::File::options();
+            match append {
+                None => options.read(true),
+                Some(true) => options.append(true).create(true),
+                Some(false) => options.write(true).truncate(true).create(true),
+            };
+            let path = path.clone();
+            tokio::task::spawn_blocking(move || options.open(&path))
+                .await
+                .map_err(std::io::Error::other)
+                .flatten()
+        }
+    }
+    .map_err(|e| Error::Io(format!("failed to open `{}`: {}", path.display(), e)))?;
+
+    Ok(Stdio::Fd(Arc::new(file.into())))
+}
+
+/// Opens a file as the given user, in a helper process which has dropped privileges to the user, including supplementary
+/// groups, so that permissions are checked exactly as they are for the command. The opened file descriptor is passed back
+/// over a socket.
+async fn open_as(
+    path: &Path,
+    append: Option<bool>,
+    uid: u32,
+    gid: u32,
+    groups: Vec<u32>,
+) -> std::io::Result<std::fs::File> {
+    let flags = libc::O_CLOEXEC
+        | libc::O_NOCTTY
+        | match append {
+            None => libc::O_RDONLY,
+            Some(true) => libc::O_WRONLY | libc::O_APPEND | libc::O_CREAT,
+            Some(false) => libc::O_WRONLY | libc::O_TRUNC | libc::O_CREAT,
+        };
+    let path = CString::new(path.as_os_str().as_bytes())?;
+    let (receiver, sender) = UnixStream::pair()?;
+
+    // The helper is subscribed before the child queue gets a chance to reap it, so that its exit status is not lost.
+    let lock = airupfx::process::lock().await;
+    let pid = match unsafe { libc::fork() } {
+        -1 => return Err(std::io::Error::last_os_error()),
+        0 => unsafe { open_as_helper(sender.as_raw_fd(), &path, flags, uid, gid, &groups) },
+        pid => pid,
+    };
+    let helper = airupfx::process::Child::from_pid(pid as _);
+    drop(lock);
+    drop(sender);
+
+    let file = tokio::task::spawn_blocking(move || recv_fd(&receiver))
+        .await
+        .map_err(std::io::Error::other)
+        .flatten();
+    if let Ok(helper) = helper {
+        _ = helper.wait().await;
+    }
+    file
+}
+
+/// Body of the helper process forked by [`open_as`], which sends the opened file descriptor, or the error number if it fails,
+/// through the given socket.
+///
+/// # Safety
+/// This must only be called in a newly forked child, and it only calls async-signal-safe functions.
+unsafe fn open_as_helper(
+    sock: RawFd,
+    path: &CStr,
+    flags: libc::c_int,
+    uid: u32,
+    gid: u32,
+    groups: &[u32],
+) -> ! {
+    unsafe {
+        let fd = match libc::setgroups(groups.len() as _, groups.as_ptr()) == 0
+            && libc::setgid(gid) == 0
+            && libc::setuid(uid) == 0
+        {
+            true => libc::open(path.as_ptr(), flags, 0o666 as libc::c_uint),
+            false => -1,
+        };
+        let errno = match fd {
+            -1 => std::io::Error::last_os_error().raw_os_error().unwrap_or(0),
+            _ => 0,
+        };
+
+        let mut data = errno.to_ne_bytes();
+        let mut iov = libc::iovec {
+            iov_base: data.as_mut_ptr().cast(),
+            iov_len: data.len(),
+        };
+        let mut control = [0u64; 4];
+        let mut msg: libc::msghdr = std::mem::zeroed();
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        if fd != -1 {
+            msg.msg_control = control.as_mut_ptr().cast();
+            msg.msg_controllen = libc::CMSG_SPACE(size_of::<libc::c_int>() as _) as _;
+            let cmsg = libc::CMSG_FIRSTHDR(&msg);
+            (*cmsg).cmsg_level = libc::SOL_SOCKET;
+            (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+            (*cmsg).cmsg_len = libc::CMSG_LEN(size_of::<libc::c_int>() as _) as _;
+            libc::CMSG_DATA(cmsg)
+                .cast::<libc::c_int>()
+                .write_unaligned(fd);
+        }
+        libc::sendmsg(sock, &msg, 0);
+        libc::_exit(0)
+    }
+}
+
+/// Receives a file descriptor sent by [`open_as_helper`].
+fn recv_fd(sock: &UnixStream) -> std::io::Result<std::fs::File> {
+    let mut data = [0u8; size_of::<libc::c_int>()];
+    let mut iov = libc::iovec {
+        iov_base: data.as_mut_ptr().cast(),
+        iov_len: data.len(),
+    };
+    let mut control = [0u64; 4];
+    let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.as_mut_ptr().cast();
+    msg.msg_controllen = size_of_val(&control) as _;
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    let flags = libc::MSG_CMSG_CLOEXEC;
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    let flags = 0;
+    let len = unsafe { libc::recvmsg(sock.as_raw_fd(), &mut msg, flags) };
+    if len < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    if len as usize != data.len() {
+        return Err(std::io::Error::other(
+            "the helper process exited unexpectedly",
+        ));
+    }
+    match libc::c_int::from_ne_bytes(data) {
+        0 => (),
+        errno => return Err(std::io::Error::from_raw_os_error(errno)),
+    }
+
+    let cmsg = unsafe { libc::CMSG_FIRSTHDR(&msg) };
+    if cmsg.is_null()
+        || unsafe { ((*cmsg).cmsg_level, (*cmsg).cmsg_type) }
+            != (libc::SOL_SOCKET, libc::SCM_RIGHTS)
+    {
+        return Err(std::io::Error::other(
+            "the helper process sent no file descriptor",
+        ));
+    }
+    let fd = unsafe { libc::CMSG_DATA(cmsg).cast::<libc::c_int>().read_unaligned() };
+    let file = unsafe { std::fs::File::from_raw_fd(fd) };
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    unsafe {
+        libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC);
+    }
+    Ok(file)
+}
+
 #[derive(Debug, Clone)]
 pub struct Modules {
     builtins: HashMap<&'static str, builtins::BuiltinModule>,
@@ -123,15 +695,34 @@ pub enum Child {
     AlwaysSuccess(Box<Self>),
     Process(airupfx::process::Child),
     Builtin(tokio::sync::Mutex<JoinHandle<i32>>),
+
+    /// A pipeline. Its exit status is the last command's.
+    Pipeline(Vec<Self>),
+
+    /// A compound child, e.g. a list of pipelines joined by `&&`, `||` or `;`.
+    Compound(Arc<Compound>),
 }
 impl Child {
     /// Returns process ID of the child.
-    pub const fn id(&self) -> i64 {
+    ///
+    /// For a pipeline, this is the last command's process ID. For a compound child, this is the process ID of the child that
+    /// is currently running.
+    pub fn id(&self) -> i64 {
         match self {
             Self::Async(child) => child.id(),
             Self::AlwaysSuccess(child) => child.id(),
             Self::Process(proc) => proc.id(),
             Self::Builtin(_) => 0,
+            Self::Pipeline(children) => children.last().map_or(0, Self::id),
+            Self::Compound(compound) => compound.current().id(),
+        }
+    }
+
+    /// Prevents the child from running further commands, if it is a compound child. Commands that are already running are
+    /// not affected.
+    pub fn abort(&self) {
+        if let Self::Compound(compound) = self {
+            compound.aborted.store(true, atomic::Ordering::Release);
         }
     }
 
@@ -140,15 +731,25 @@ impl Child {
     pub async fn wait(&self) -> Result<Wait, Error> {
         Ok(match self {
             Self::Async(child) => Wait::new(child.id(), ExitStatus::SUCCESS),
-            Self::AlwaysSuccess(child) => {
-                let mut wait = Box::pin(child.wait()).await?;
-                wait.exit_status = ExitStatus::SUCCESS;
-                wait
-            }
+            Self::AlwaysSuccess(child) => match Box::pin(child.wait()).await {
+                Ok(mut wait) => {
+                    wait.exit_status = ExitStatus::SUCCESS;
+                    wait
+                }
+                Err(_) => Wait::new(child.id(), ExitStatus::SUCCESS),
+            },
             Self::Process(proc) => proc.wait().await?,
             Self::Builtin(builtin) => {
                 Wait::new(0, builtins::wait(&mut *builtin.lock().await).await)
             }
+            Self::Pipeline(children) => {
+                let mut wait = Wait::new(0, ExitStatus::SUCCESS);
+                for child in children {
+                    wait = Box::pin(child.wait()).await?;
+                }
+                wait
+            }
+            Self::Compound(compound) => compound.wait().await?,
         })
     }
 
@@ -182,6 +783,13 @@ impl Child {
             Self::AlwaysSuccess(child) => Box::pin(child.send_signal(sig)).await?,
             Self::Process(proc) => proc.send_signal(sig).await?,
             Self::Builtin(builtin) => builtin.lock().await.abort(),
+            Self::Pipeline(children) => {
+                // Some commands of the pipeline may have exited, which should not prevent others from being signaled.
+                for child in children {
+                    _ = Box::pin(child.send_signal(sig)).await;
+                }
+            }
+            Self::Compound(compound) => Box::pin(compound.current().send_signal(sig)).await?,
         };
 
         Ok(())
@@ -195,22 +803,47 @@ impl Child {
             Self::AlwaysSuccess(child) => Box::pin(child.kill()).await?,
             Self::Process(proc) => proc.kill().await?,
             Self::Builtin(builtin) => builtin.lock().await.abort(),
+            Self::Pipeline(children) => {
+                for child in children {
+                    _ = Box::pin(child.kill()).await;
+                }
+            }
+            Self::Compound(compound) => {
+                self.abort();
+                Box::pin(compound.current().kill()).await?;
+            }
         };
 
         Ok(())
     }
 
-    /// Attempts to kill the process with given signal number. If the process did not terminate in specified time, it will be
-    /// forcefully killed.
-    pub async fn kill_timeout(&self, sig: i32, timeout: Option<Duration>) -> Result<(), Error> {
+    /// Attempts to kill the process with given signal number. If the process did not terminate in specified time, `kill_sig`
+    /// is sent to it. If `kill_sig` is not `SIGKILL`, and the process still did not terminate in [`KILL_SIGNAL_TIMEOUT`], it
+    /// is killed.
+    pub async fn kill_timeout(
+        &self,
+        sig: i32,
+        kill_sig: i32,
+        timeout: Option<Duration>,
+    ) -> Result<(), Error> {
+        self.abort();
         self.send_signal(sig).await?;
         match self.wait_timeout(timeout).await {
-            Ok(_) => Ok(()),
-            Err(err) => match err {
-                Error::TimedOut => self.kill().await,
-                other => Err(other),
-            },
+            Ok(_) => return Ok(()),
+            Err(Error::TimedOut) => (),
+            Err(err) => return Err(err),
         }
+
+        if kill_sig != SIGKILL {
+            self.send_signal(kill_sig).await?;
+            match self.wait_timeout(Some(KILL_SIGNAL_TIMEOUT)).await {
+                Ok(_) => return Ok(()),
+                Err(Error::TimedOut) => (),
+                Err(err) => return Err(err),
+            }
+        }
+
+        self.kill().await
     }
 }
 impl From<airupfx::process::Child> for Child {
@@ -219,12 +852,79 @@ impl From<airupfx::process::Child> for Child {
     }
 }
 
+/// A compound child, which runs further commands in background after the first one, e.g. a list of pipelines, or a
+/// command with `@retry` or `@timeout`.
+///
+/// The compound child stops running further commands if it was aborted by [`Child::abort`].
+#[derive(Debug)]
+pub struct Compound {
+    current: Mutex<Arc<Child>>,
+    aborted: AtomicBool,
+    done: watch::Sender<Option<Result<Wait, Error>>>,
+}
+impl Compound {
+    /// Starts a compound child with the given first child. Then the given function is run in background, whose result is
+    /// the result of the compound child.
+    fn start<F, Fut>(first: Child, f: F) -> Arc<Self>
+    where
+        F: FnOnce(Arc<Self>) -> Fut,
+        Fut: Future<Output = Result<Wait, Error>> + Send + 'static,
+    {
+        let compound = Arc::new(Self {
+            current: Mutex::new(Arc::new(first)),
+            aborted: AtomicBool::new(false),
+            done: watch::channel(None).0,
+        });
+        let future = f(Arc::clone(&compound));
+        let cloned = Arc::clone(&compound);
+        tokio::spawn(async move {
+            let result = future.await;
+            cloned.done.send_replace(Some(result));
+        });
+        compound
+    }
+
+    /// Returns the child that is currently running, or the last one that ran.
+    fn current(&self) -> Arc<Child> {
+        Arc::clone(&self.current.lock().unwrap())
+    }
+
+    /// Replaces the current child. If the compound child has been aborted, the new child is killed.
+    async fn set_current(&self, child: Child) -> Result<Arc<Child>, Error> {
+        let child = Arc::new(child);
+        *self.current.lock().unwrap() = Arc::clone(&child);
+        if self.is_aborted() {
+            child.kill().await?;
+        }
+        Ok(child)
+    }
+
+    fn is_aborted(&self) -> bool {
+        self.aborted.load(atomic::Ordering::Acquire)
+    }
+
+    async fn wait(&self) -> Result<Wait, Error> {
+        let mut receiver = self.done.subscribe();
+        let x = receiver
+            .wait_for(|x| x.is_some())
+            .await
+            .expect("the sender is owned by `self`");
+
+        x.as_ref()
+            .expect("`watch::Receiver::wait_for` should only return expected value")
+            .clone()
+    }
+}
+
 /// An error occured by ACE operations.
 #[derive(Debug, Clone, thiserror::Error)]
 pub enum Error {
     #[error("parse error: {0}")]
     Parse(String),
 
+    #[error("{0}")]
+    Expand(String),
+
     #[error("wait() failed: {0}")]
     Wait(WaitError),
 
@@ -247,7 +947,8 @@ impl From<WaitError> for Error {
 impl IntoApiError for Error {
     fn into_api_error(self) -> airup_sdk::Error {
         match self {
-            Self::Parse(_) => airup_sdk::Error::AceParseError,
+            Self::Parse(message) => airup_sdk::Error::AceParseError { message },
+            Self::Expand(message) => airup_sdk::Error::Custom { message },
             Self::Wait(err) => airup_sdk::Error::internal(err.to_string()),
             Self::Io(message) => airup_sdk::Error::Io { message },
             Self::TimedOut => airup_sdk::Error::TimedOut,
@@ -290,3 +991,87 @@ impl IntoApiError for CommandExitError {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::parser::Command;
+
+    fn command(s: &str) -> Command {
+        let mut words = s.split_whitespace().map(String::from);
+        Command {
+            module: words.next().unwrap(),
+            args: words.collect(),
+        }
+    }
+
+    #[test]
+    fn modifier_chain() {
+        let cmd = command("@nofail @user nobody @retry 3 100 fs.write x y");
+        let (chain, module) = super::modifier_chain(&cmd);
+        let chain: Vec<_> = chain
+            .iter()
+            .map(|(name, params)| (*name, params.len()))
+            .collect();
+        assert_eq!(chain, [("@nofail", 0), ("@user", 1), ("@retry", 2)]);
+        assert_eq!(module, Some("fs.write"));
+
+        assert_eq!(
+            super::modifier_chain(&command("echo @user")).1,
+            Some("echo")
+        );
+        assert_eq!(super::modifier_chain(&command("@cwd /tmp")).1, None);
+    }
+
+    #[test]
+    fn target_env() {
+        let mut env = airupfx::process::CommandEnv::new();
+        env.working_dir("/srv");
+        let target = super::target_env(&command("@cwd data @user 0 @env A=1 ls"), &env).unwrap();
+        assert_eq!(target.working_dir.as_deref(), Some("/srv/data".as_ref()));
+        assert_eq!(target.uid, Some(0));
+    }
+
+    #[tokio::test]
+    async fn open_redirect() {
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+        // Redirects are only opened as another user if `airupd` is privileged
+        if unsafe { libc::geteuid() } != 0 {
+            return;
+        }
+        let dir = std::env::temp_dir().join(format!("airupd-test-redirect-{}", std::process::id()));
+        std::fs::create_dir(&dir).unwrap();
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o1777)).unwrap();
+        let group_only = dir.join("group-only");
+        std::fs::create_dir(&group_only).unwrap();
+        std::fs::set_permissions(&group_only, std::fs::Permissions::from_mode(0o770)).unwrap();
+
+        let mut env = airupfx::process::CommandEnv::new();
+        env.working_dir(&dir);
+        let target = super::target_env(&command("@user nobody true"), &env).unwrap();
+
+        // The file is created by the target user
+        super::open_redirect(&target, "out", Some(false))
+            .await
+            .unwrap();
+        let metadata = std::fs::metadata(dir.join("out")).unwrap();
+        assert_eq!(Some(metadata.uid()), target.uid);
+
+        // Groups of `airupd` are not granted to the target user
+        assert!(
+            super::open_redirect(&target, "group-only/out", Some(false))
+                .await
+                .is_err()
+        );
+        assert!(
+            super::open_redirect(&env, "group-only/out", Some(false))
+                .await
+                .is_ok()
+        );
+
+        // A UID without an entry in the user database is rejected
+        assert!(super::set_user(&mut env.clone(), "4294967290").is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
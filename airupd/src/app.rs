@@ -52,15 +52,19 @@ impl Airupd {
             .query_milestone_stack()
             .last()
             .map(|x| x.finish_timestamp);
+        let is_booting = self.is_booting();
+        let failed_items = self.milestones.failed_items();
 
         QuerySystem {
             status: Status::Active,
             boot_timestamp: self.boot_timestamp,
             booted_since,
-            is_booting: self.is_booting(),
+            is_booting,
             milestones: self.milestones.stack(),
             instance_name: airupfx::env::instance_name(),
             services: self.supervisors.list().await,
+            is_degraded: !is_booting && !failed_items.is_empty(),
+            failed_items,
         }
     }
 
@@ -91,7 +95,7 @@ impl Airupd {
             tracing::warn!(target: "console", "Too many signals were received. Performing a forced reboot.");
             self.lifetime.reboot();
         } else if counter == 0 {
-            _ = self.enter_milestone("reboot".into()).await;
+            _ = self.enter_milestone("reboot".into(), false).await;
         }
     }
 
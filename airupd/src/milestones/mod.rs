@@ -4,32 +4,37 @@ pub mod early_boot;
 mod reboot;
 
 use crate::{
-    ace::Ace,
+    ace::{Ace, Child},
     app::{self, airupd},
+    supervisor::task::TaskHandle,
 };
 use airup_sdk::{
     Error,
     files::{
         Milestone,
-        milestone::{Item, Kind},
+        milestone::{Entry, Item, Kind, OnFailure},
     },
     prelude::*,
-    system::{EnteredMilestone, ServiceStartReason},
+    system::{EnteredMilestone, FailedItem, QueryMilestone, ServiceStartReason},
 };
 use std::{
     cell::LazyCell,
     collections::HashSet,
+    fmt::Display,
     sync::{
-        RwLock,
+        Arc, RwLock,
         atomic::{self, AtomicBool},
     },
 };
+use tokio::task::JoinSet;
 
 /// The milestone manager.
 #[derive(Debug, Default)]
 pub struct Manager {
     is_booting: AtomicBool,
+    falling_back: AtomicBool,
     stack: RwLock<Vec<EnteredMilestone>>,
+    failed_items: RwLock<Vec<FailedItem>>,
 }
 impl Manager {
     /// Creates a new [`Manager`] instance.
@@ -41,17 +46,44 @@ impl Manager {
     pub fn stack(&self) -> Vec<EnteredMilestone> {
         self.stack.read().unwrap().clone()
     }
+
+    /// Returns items that failed when entering milestones during booting.
+    pub fn failed_items(&self) -> Vec<FailedItem> {
+        self.failed_items.read().unwrap().clone()
+    }
 }
 
 impl crate::app::Airupd {
     /// Enters the specific milestone.
-    pub async fn enter_milestone(&self, name: String) -> Result<(), Error> {
+    ///
+    /// If `isolate` is `true`, or the milestone is marked as `isolate`, services that are not required by the milestone or
+    /// its dependencies are stopped after entering it, and the milestone stack is replaced by milestones entered in this
+    /// transition.
+    pub async fn enter_milestone(&self, name: String, isolate: bool) -> Result<(), Error> {
         let name = name.strip_suffix(".airm").unwrap_or(&name).to_owned();
         if reboot::PRESETS.contains(&&name[..]) {
-            reboot::enter(&name).await
-        } else {
-            enter_milestone(name, &mut HashSet::with_capacity(8)).await
+            return reboot::enter(&name).await;
+        }
+
+        let isolate = isolate
+            || self
+                .storage
+                .milestones
+                .get(&name)
+                .await
+                .is_ok_and(|x| x.manifest.milestone.isolate);
+        let depth = self.milestones.stack.read().unwrap().len();
+        let mut hist = HashSet::with_capacity(8);
+        enter_milestone(name, &mut hist).await?;
+
+        if isolate {
+            stop_services_except(&required_services(&hist).await).await;
+            let mut stack = self.milestones.stack.write().unwrap();
+            let depth = depth.min(stack.len());
+            stack.drain(..depth);
         }
+
+        Ok(())
     }
 
     /// Enters the specific milestone as bootstrap milestone.
@@ -60,8 +92,9 @@ impl crate::app::Airupd {
             self.milestones
                 .is_booting
                 .store(true, atomic::Ordering::Relaxed);
+            self.milestones.failed_items.write().unwrap().clear();
 
-            _ = self.enter_milestone(name).await;
+            _ = self.enter_milestone(name, false).await;
 
             self.milestones
                 .is_booting
@@ -74,6 +107,41 @@ impl crate::app::Airupd {
         self.milestones.is_booting.load(atomic::Ordering::Relaxed)
     }
 
+    /// Queries information about the specific milestone.
+    pub async fn query_milestone(&self, name: &str) -> Result<QueryMilestone, Error> {
+        let name = name.strip_suffix(".airm").unwrap_or(name);
+        let def = self.storage.milestones.get(name).await?;
+        let entered = self
+            .milestones
+            .stack
+            .read()
+            .unwrap()
+            .iter()
+            .rev()
+            .find(|x| x.name == name || x.name == def.name)
+            .cloned();
+
+        let mut services = Vec::new();
+        for service in self.supervisors.list().await {
+            let Ok(queried) = self.query_service(&service).await else {
+                continue;
+            };
+            if matches!(&queried.start_reason, Some(ServiceStartReason::Milestone(x)) if *x == def.name)
+            {
+                services.push(service);
+            }
+        }
+        services.sort_unstable();
+
+        Ok(QueryMilestone {
+            items: def.items().await,
+            name: def.name,
+            manifest: def.manifest,
+            entered,
+            services,
+        })
+    }
+
     /// Queries the milestone stack.
     pub fn query_milestone_stack(&self) -> Vec<EnteredMilestone> {
         self.milestones.stack.read().unwrap().clone()
@@ -103,9 +171,14 @@ async fn enter_milestone(name: String, hist: &mut HashSet<String>) -> Result<(),
     tracing::info!(target: "console", "Entering milestone {}", def.display_name());
     let begin_timestamp = airupfx::time::timestamp_ms();
 
-    // Enters dependency milestones
+    // Enters dependency milestones. If a dependency failed and diverted to its fallback, entering this milestone is
+    // aborted as well.
     for dep in def.manifest.milestone.dependencies.iter() {
-        _ = Box::pin(enter_milestone(dep.into(), hist)).await;
+        if let Err(err @ Error::MilestoneFailed { .. }) =
+            Box::pin(enter_milestone(dep.into(), hist)).await
+        {
+            return Err(err);
+        }
     }
 
     // By default, Airup sets `AIRUP_MILESTONE` environment variable to indicate services which milestone is the system
@@ -117,7 +190,38 @@ async fn enter_milestone(name: String, hist: &mut HashSet<String>) -> Result<(),
     }
 
     // Starts services
-    exec_milestone(&def).await;
+    let failed = exec_milestone(&def).await;
+    let required_failed = failed.iter().any(|x| x.item.required);
+    if airupd().is_booting() {
+        airupd()
+            .milestones
+            .failed_items
+            .write()
+            .unwrap()
+            .extend(failed);
+    }
+    if required_failed {
+        let target = match def.manifest.milestone.on_failure {
+            OnFailure::Continue => None,
+            OnFailure::Fallback => Some(def.manifest.milestone.fallback()),
+            OnFailure::Reboot => Some("reboot"),
+        };
+        match target {
+            Some(target) => {
+                tracing::error!(
+                    target: "console",
+                    "Required items of milestone {} failed. Entering milestone `{}`",
+                    def.display_name(),
+                    target
+                );
+                fall_back(target).await;
+                return Err(Error::MilestoneFailed { name });
+            }
+            None => {
+                tracing::warn!(target: "console", "Required items of milestone {} failed", def.display_name())
+            }
+        }
+    }
 
     // Record the milestone as entered
     let finish_timestamp = airupfx::time::timestamp_ms();
@@ -131,7 +235,20 @@ async fn enter_milestone(name: String, hist: &mut HashSet<String>) -> Result<(),
     Ok(())
 }
 
-async fn exec_milestone(def: &Milestone) {
+/// Enters the fallback milestone of a failed milestone.
+///
+/// If a fallback milestone fails when falling back, its own fallback is not entered, which avoids fallback loops.
+async fn fall_back(target: &str) {
+    let falling_back = &airupd().milestones.falling_back;
+    if falling_back.swap(true, atomic::Ordering::SeqCst) {
+        tracing::error!(target: "console", "Already falling back. Milestone `{}` is not entered", target);
+        return;
+    }
+    _ = Box::pin(airupd().enter_milestone(target.into(), false)).await;
+    falling_back.store(false, atomic::Ordering::SeqCst);
+}
+
+async fn exec_milestone(def: &Milestone) -> Vec<FailedItem> {
     match def.manifest.milestone.kind {
         Kind::Async => exec_milestone_async(def).await,
         Kind::Serial => exec_milestone_serial(def).await,
@@ -139,107 +256,218 @@ async fn exec_milestone(def: &Milestone) {
     }
 }
 
-async fn exec_milestone_async(def: &Milestone) {
+async fn exec_milestone_async(def: &Milestone) -> Vec<FailedItem> {
     let ace = LazyCell::new(Ace::new);
-    for item in def.items().await {
-        match item {
+    let mut failed = Vec::new();
+    let mut commands = Vec::new();
+    let mut handles = Vec::new();
+    for entry in def.items().await {
+        match &entry.item {
             Item::Cache(service) => {
-                if let Err(err) = app::airupd().cache_service(&service).await {
+                if let Err(err) = app::airupd().cache_service(service).await {
                     tracing::error!(target: "console", "Failed to load service {}: {}", service, err);
+                    failed.push(failed_item(def, &entry, err));
                 }
             }
-            Item::Start(service) => match app::airupd().start_service(&service).await {
+            Item::Start(service) => match app::airupd().start_service(service).await {
+                Ok(x) if entry.required => {
+                    handles.push((service.clone(), entry.clone(), x));
+                }
                 Ok(_) => {
-                    tracing::info!(target: "console", "Starting {}", display_name(&service).await)
+                    tracing::info!(target: "console", "Starting {}", display_name(service).await)
                 }
                 Err(err) => {
-                    tracing::error!(target: "console", "Failed to start {}: {}", service, err)
+                    tracing::error!(target: "console", "Failed to start {}: {}", service, err);
+                    failed.push(failed_item(def, &entry, err));
                 }
             },
-            Item::Run(cmd) => {
-                if let Err(err) = ace.run(&cmd).await {
+            Item::Run(cmd) => match ace.run(cmd).await {
+                Ok(x) if entry.required => {
+                    commands.push((cmd.clone(), entry.clone(), x));
+                }
+                Ok(_) => {}
+                Err(err) => {
                     tracing::error!(target: "console", "Failed to execute command `{cmd}`: {}", err);
+                    failed.push(failed_item(def, &entry, err));
                 }
-            }
+            },
         }
     }
+
+    // Results of required items are waited for, so that their failures can be detected
+    wait_items(def, handles, commands, &mut failed).await;
+
+    failed
 }
 
-async fn exec_milestone_serial(def: &Milestone) {
+async fn exec_milestone_serial(def: &Milestone) -> Vec<FailedItem> {
     let ace = LazyCell::new(Ace::new);
-    for item in def.items().await {
-        match item {
+    let mut failed = Vec::new();
+    for entry in def.items().await {
+        match &entry.item {
             Item::Cache(service) => {
-                if let Err(err) = app::airupd().cache_service(&service).await {
+                if let Err(err) = app::airupd().cache_service(service).await {
                     tracing::error!(target: "console", "Failed to load service {}: {}", service, err);
+                    failed.push(failed_item(def, &entry, err));
                 }
             }
             Item::Start(service) => match app::airupd()
-                .autostart_service(&service, ServiceStartReason::Milestone(def.name.clone()))
+                .autostart_service(service, ServiceStartReason::Milestone(def.name.clone()))
                 .await
             {
-                Ok(_) => {
-                    tracing::info!(target: "console", "Starting {}", display_name(&service).await)
-                }
+                Ok(_) => report_started(service).await,
                 Err(err) => {
-                    tracing::error!(target: "console", "Failed to start {}: {}", display_name(&service).await, err);
+                    tracing::error!(target: "console", "Failed to start {}: {}", display_name(service).await, err);
+                    failed.push(failed_item(def, &entry, err));
                 }
             },
             Item::Run(cmd) => {
-                if let Err(err) = run_wait(&ace, &cmd).await {
+                if let Err(err) = run_wait(&ace, cmd).await {
                     tracing::error!(target: "console", "Failed to execute command `{cmd}`: {}", err);
+                    failed.push(failed_item(def, &entry, err));
                 }
             }
         }
     }
+
+    failed
 }
 
-async fn exec_milestone_sync(def: &Milestone) {
+async fn exec_milestone_sync(def: &Milestone) -> Vec<FailedItem> {
     let ace = LazyCell::new(Ace::new);
     let items = def.items().await;
+    let mut failed = Vec::new();
     let mut commands = Vec::with_capacity(items.len());
     let mut handles = Vec::with_capacity(items.len());
-    for item in items {
-        match item {
+    for entry in items {
+        match &entry.item {
             Item::Cache(service) => {
-                if let Err(err) = app::airupd().cache_service(&service).await {
+                if let Err(err) = app::airupd().cache_service(service).await {
                     tracing::error!(target: "console", "Failed to load service {}: {}", service, err);
+                    failed.push(failed_item(def, &entry, err));
                 }
             }
-            Item::Start(service) => match app::airupd().start_service(&service).await {
+            Item::Start(service) => match app::airupd().start_service(service).await {
                 Ok(x) => {
-                    handles.push((service, x));
+                    handles.push((service.clone(), entry.clone(), x));
                 }
                 Err(err) => {
                     tracing::error!(target: "console", "Failed to start {}: {}", service, err);
+                    failed.push(failed_item(def, &entry, err));
                 }
             },
-            Item::Run(cmd) => match ace.run(&cmd).await {
+            Item::Run(cmd) => match ace.run(cmd).await {
                 Ok(x) => {
-                    commands.push((cmd, x));
+                    commands.push((cmd.clone(), entry.clone(), x));
                 }
                 Err(err) => {
                     tracing::error!(target: "console", "Failed to execute command `{cmd}`: {}", err);
+                    failed.push(failed_item(def, &entry, err));
                 }
             },
         }
     }
 
-    for (name, handle) in handles {
+    wait_items(def, handles, commands, &mut failed).await;
+
+    failed
+}
+
+/// Waits for started services and running commands of a milestone, recording failed ones.
+async fn wait_items(
+    def: &Milestone,
+    handles: Vec<(String, Entry, Arc<dyn TaskHandle>)>,
+    commands: Vec<(String, Entry, Child)>,
+    failed: &mut Vec<FailedItem>,
+) {
+    for (name, entry, handle) in handles {
         match handle.wait().await {
-            Ok(_) | Err(Error::Started) => {
-                tracing::info!(target: "console", "Starting {}", display_name(&name).await)
-            }
+            Ok(_) | Err(Error::Started) => report_started(&name).await,
             Err(err) => {
                 tracing::error!(target: "console", "Failed to start {}: {}", display_name(&name).await, err);
+                failed.push(failed_item(def, &entry, err));
             }
         }
     }
 
-    for (cmd, child) in commands {
+    for (cmd, entry, child) in commands {
         if let Err(err) = child.wait().await {
             tracing::error!(target: "console", "Failed to execute command `{cmd}`: {}", err);
+            failed.push(failed_item(def, &entry, err));
+        }
+    }
+}
+
+fn failed_item(def: &Milestone, entry: &Entry, err: impl Display) -> FailedItem {
+    FailedItem {
+        milestone: def.name.clone(),
+        item: entry.clone(),
+        error: err.to_string(),
+    }
+}
+
+/// Returns names of services that are required by the given milestones, including their dependencies.
+async fn required_services(milestones: &HashSet<String>) -> HashSet<String> {
+    let mut pending = Vec::new();
+    for name in milestones {
+        let Ok(def) = app::airupd().storage.milestones.get(name).await else {
+            continue;
+        };
+        for entry in def.items().await {
+            if let Item::Cache(service) | Item::Start(service) = entry.item {
+                pending.push(service);
+            }
+        }
+    }
+
+    let mut required = HashSet::with_capacity(pending.len());
+    while let Some(name) = pending.pop() {
+        let Ok(queried) = app::airupd().query_service(&name).await else {
+            continue;
+        };
+        let service = queried.definition;
+        if required.insert(service.name) {
+            pending.extend(service.service.dependencies);
+        }
+    }
+
+    required
+}
+
+/// Stops all active services, except the given ones.
+async fn stop_services_except(required: &HashSet<String>) {
+    let mut join_set = JoinSet::new();
+    for service in app::airupd().supervisors.list().await {
+        if required.contains(&service) {
+            continue;
+        }
+        join_set.spawn(async move {
+            let result = match app::airupd().stop_service(&service).await {
+                Ok(handle) => handle.wait().await.map(|_| ()),
+                Err(err) => Err(err),
+            };
+            match result {
+                Ok(()) => {
+                    tracing::info!(target: "console", "Stopped {}", display_name(&service).await)
+                }
+                Err(Error::NotFound | Error::NotStarted | Error::Unsupported { .. }) => {}
+                Err(err) => {
+                    tracing::error!(target: "console", "Failed to stop {}: {}", display_name(&service).await, err)
+                }
+            }
+        });
+    }
+    join_set.join_all().await;
+}
+
+/// Reports that the service has been started, or skipped if any of its conditions is not met.
+async fn report_started(name: &str) {
+    match app::airupd().query_service(name).await {
+        Ok(queried) if queried.skipped.is_some() => {
+            let reason = queried.skipped.as_deref().unwrap_or_default();
+            tracing::info!(target: "console", "Skipped {}: {}", queried.definition.display_name(), reason)
         }
+        _ => tracing::info!(target: "console", "Starting {}", display_name(name).await),
     }
 }
 
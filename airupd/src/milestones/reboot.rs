@@ -28,8 +28,11 @@ pub async fn enter(name: &str) -> Result<(), Error> {
 }
 
 /// Stops all running services.
+///
+/// Each service is stopped with its own stop signal, kill mode and grace period. Services that did not stop before the timeout
+/// expired are forced to stop with their kill signal.
 async fn stop_all_services(timeout: Duration) {
-    _ = tokio::time::timeout(timeout, async {
+    let stopping = tokio::time::timeout(timeout, async {
         let services = airupd().supervisors.list().await;
         let mut join_set = JoinSet::new();
         for service in services {
@@ -38,6 +41,12 @@ async fn stop_all_services(timeout: Duration) {
         join_set.join_all().await;
     })
     .await;
+
+    if stopping.is_err() {
+        for service in airupd().supervisors.list().await {
+            _ = airupd().kill_service(&service).await;
+        }
+    }
 }
 
 /// Spawns a task to interactively stop a service.
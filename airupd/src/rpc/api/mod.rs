@@ -3,7 +3,7 @@
 mod debug;
 mod info;
 pub mod session;
-mod system;
+pub mod system;
 
 use crate::rpc::route::Router;
 use airup_sdk::{Error, rpc::Request};
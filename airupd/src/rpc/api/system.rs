@@ -1,12 +1,17 @@
 //! APIs that provides system operations.
 
 use super::MethodFuture;
-use crate::{app::airupd, rpc::route::Router};
+use crate::{app::airupd, rpc::route::Router, supervisor::ExecCommand};
 use airup_sdk::{
     Error,
     files::Service,
-    system::{Event, QueryService, QuerySystem},
+    rpc::Request,
+    system::{
+        Event, ExitStatus, Job, JobKind, QueryMilestone, QueryService, QuerySystem, RestartMode,
+        ServiceProcess,
+    },
 };
+use tokio::io::AsyncReadExt;
 
 pub fn router() -> Router {
     Router::new()
@@ -18,15 +23,26 @@ pub fn router() -> Router {
         .route("stop_service", stop_service)
         .route("kill_service", kill_service)
         .route("reload_service", reload_service)
+        .route("restart_service", restart_service)
+        .route("submit_job", submit_job)
+        .route("list_jobs", list_jobs)
+        .route("wait_job", wait_job)
+        .route("cancel_job", cancel_job)
+        .route("list_service_processes", list_service_processes)
+        .route("signal_service", signal_service)
         .route("sideload_service", sideload_service)
         .route("cache_service", cache_service)
         .route("uncache_service", uncache_service)
         .route("interrupt_service_task", interrupt_service_task)
         .route("list_services", list_services)
+        .route("list_milestones", list_milestones)
+        .route("query_milestone", query_milestone)
         .route("enter_milestone", enter_milestone)
+        .route("isolate_milestone", isolate_milestone)
         .route("set_instance_name", set_instance_name)
         .route("trigger_event", trigger_event)
         .route("unregister_extension", unregister_extension)
+        .route("expand_ace", expand_ace)
 }
 
 #[airupfx::macros::api]
@@ -74,12 +90,52 @@ async fn kill_service(service: String) -> Result<(), Error> {
     airupd().kill_service(&service).await
 }
 
+#[airupfx::macros::api]
+async fn restart_service(service: String, mode: RestartMode) -> Result<(), Error> {
+    airupd()
+        .restart_service(&service, mode)
+        .await?
+        .wait()
+        .await?;
+    Ok(())
+}
+
+#[airupfx::macros::api]
+async fn submit_job(service: String, kind: JobKind) -> Result<u64, Error> {
+    Ok(airupd().submit_job(&service, kind).await?.id())
+}
+
+#[airupfx::macros::api]
+async fn list_jobs() -> Result<Vec<Job>, Error> {
+    Ok(airupd().list_jobs())
+}
+
+#[airupfx::macros::api]
+async fn wait_job(id: u64) -> Result<(), Error> {
+    airupd().wait_job(id).await
+}
+
+#[airupfx::macros::api]
+async fn cancel_job(id: u64) -> Result<(), Error> {
+    airupd().cancel_job(id).await
+}
+
 #[airupfx::macros::api]
 async fn reload_service(service: String) -> Result<(), Error> {
     airupd().reload_service(&service).await?.wait().await?;
     Ok(())
 }
 
+#[airupfx::macros::api]
+async fn list_service_processes(service: String) -> Result<Vec<ServiceProcess>, Error> {
+    airupd().list_service_processes(&service).await
+}
+
+#[airupfx::macros::api]
+async fn signal_service(service: String, signum: i32, all: bool) -> Result<(), Error> {
+    airupd().signal_service(&service, signum, all).await
+}
+
 #[airupfx::macros::api]
 async fn interrupt_service_task(service: String) -> Result<(), Error> {
     airupd()
@@ -110,9 +166,24 @@ async fn list_services() -> Result<Vec<String>, Error> {
     Ok(airupd().storage.services.list().await)
 }
 
+#[airupfx::macros::api]
+async fn list_milestones() -> Result<Vec<String>, Error> {
+    Ok(airupd().storage.milestones.list().await)
+}
+
+#[airupfx::macros::api]
+async fn query_milestone(name: String) -> Result<QueryMilestone, Error> {
+    airupd().query_milestone(&name).await
+}
+
 #[airupfx::macros::api]
 async fn enter_milestone(name: String) -> Result<(), Error> {
-    airupd().enter_milestone(name).await
+    airupd().enter_milestone(name, false).await
+}
+
+#[airupfx::macros::api]
+async fn isolate_milestone(name: String) -> Result<(), Error> {
+    airupd().enter_milestone(name, true).await
 }
 
 #[airupfx::macros::api]
@@ -131,3 +202,50 @@ async fn trigger_event(event: Event) -> Result<(), Error> {
 async fn unregister_extension(name: String) -> Result<(), Error> {
     airupd().extensions.unregister(&name)
 }
+
+#[airupfx::macros::api]
+async fn expand_ace(service: String, script: String) -> Result<Vec<Vec<String>>, Error> {
+    airupd().expand_ace_in_service(&service, script).await
+}
+
+/// Implements `system.exec_in_service` and `system.run_ace_in_service`. Unlike other methods, these are not routed, since
+/// they receive standard I/O file descriptors of the command from the connection, and kill the command if the connection
+/// is closed before it exits.
+pub async fn exec_in_service(
+    conn: &mut airup_sdk::nonblocking::rpc::Connection,
+    req: Request,
+) -> Result<ExitStatus, Error> {
+    let fds = conn
+        .recv_fds(3)
+        .await
+        .map_err(|x| Error::invalid_params(format!("failed to receive stdio: {x}")))?;
+    let (service, command) = match &req.method[..] {
+        "system.run_ace_in_service" => {
+            let (service, script): (String, String) = req.extract_params()?;
+            (service, ExecCommand::Ace(script))
+        }
+        _ => {
+            let (service, argv): (String, Vec<String>) = req.extract_params()?;
+            (service, ExecCommand::Argv(argv))
+        }
+    };
+    let stdio: [_; 3] = fds
+        .try_into()
+        .map_err(|_| Error::invalid_params("exactly 3 file descriptors are required"))?;
+
+    let child = airupd().exec_in_service(&service, command, stdio).await?;
+    let mut buf = [0u8; 1];
+    tokio::select! {
+        wait = child.wait() => {
+            let wait = wait?;
+            Ok(match wait.code() {
+                Some(code) => ExitStatus::Exited(code),
+                None => ExitStatus::Signaled(wait.signal().unwrap_or_default()),
+            })
+        }
+        _ = conn.as_mut().read(&mut buf) => {
+            _ = child.kill().await;
+            Err(Error::TaskInterrupted)
+        }
+    }
+}
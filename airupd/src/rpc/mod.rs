@@ -4,7 +4,7 @@ pub mod api;
 pub mod route;
 
 use crate::app::airupd;
-use airup_sdk::rpc::Request;
+use airup_sdk::rpc::{Request, Response};
 use std::path::PathBuf;
 use tokio::sync::broadcast;
 
@@ -127,6 +127,14 @@ impl Session {
                 api::session::invoke(self, req).await;
                 return Ok(());
             }
+            if matches!(
+                &req.method[..],
+                "system.exec_in_service" | "system.run_ace_in_service"
+            ) {
+                let resp = api::system::exec_in_service(&mut self.conn, req).await;
+                self.conn.send(&Response::new(resp)).await?;
+                continue;
+            }
             let resp = match req.method.strip_prefix("extapi.") {
                 Some(method) => airupd()
                     .extensions
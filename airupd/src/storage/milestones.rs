@@ -31,6 +31,20 @@ impl Milestones {
             None => Err(std::io::ErrorKind::NotFound.into()),
         }
     }
+
+    /// Returns a list of names of all installed milestones.
+    pub async fn list(&self) -> Vec<String> {
+        self.base_chain
+            .read_chain()
+            .await
+            .map(IntoIterator::into_iter)
+            .into_iter()
+            .flatten()
+            .map(|x| String::from(x.to_string_lossy()))
+            .filter(|x| !x.starts_with('.') && x.ends_with(".airm"))
+            .map(|x| x.strip_suffix(".airm").unwrap_or(&x).into())
+            .collect()
+    }
 }
 impl Default for Milestones {
     fn default() -> Self {
@@ -0,0 +1,174 @@
+//! Evaluation of service conditions and assertions.
+
+use airup_sdk::files::service::{Conditions, Virtualization};
+use airupfx::env::virt;
+
+/// Checks the given conditions. On failure, returns a description of the first condition that is not met.
+pub async fn check(conditions: &Conditions) -> Result<(), String> {
+    let unmet = |key: &str, entry: &str| format!("condition `{key} = \"{entry}\"` is not met");
+
+    for entry in &conditions.path_exists {
+        let (negate, path) = negated(entry);
+        if tokio::fs::try_exists(path).await.unwrap_or(false) == negate {
+            return Err(unmet("path-exists", entry));
+        }
+    }
+
+    for entry in &conditions.path_is_directory {
+        let (negate, path) = negated(entry);
+        let is_dir = tokio::fs::metadata(path).await.is_ok_and(|x| x.is_dir());
+        if is_dir == negate {
+            return Err(unmet("path-is-directory", entry));
+        }
+    }
+
+    for entry in &conditions.file_not_empty {
+        let (negate, path) = negated(entry);
+        let not_empty = tokio::fs::metadata(path)
+            .await
+            .is_ok_and(|x| x.is_file() && x.len() > 0);
+        if not_empty == negate {
+            return Err(unmet("file-not-empty", entry));
+        }
+    }
+
+    if !conditions.kernel_cmdline.is_empty() {
+        let cmdline = tokio::fs::read_to_string("/proc/cmdline")
+            .await
+            .unwrap_or_default();
+        for entry in &conditions.kernel_cmdline {
+            let (negate, token) = negated(entry);
+            if cmdline_contains(&cmdline, token) == negate {
+                return Err(unmet("kernel-cmdline", entry));
+            }
+        }
+    }
+
+    if let Some(expected) = conditions.virtualization {
+        let detected = virt::detect();
+        let met = match expected {
+            Virtualization::None => detected == virt::Virtualization::None,
+            Virtualization::Container => detected == virt::Virtualization::Container,
+            Virtualization::Vm => detected == virt::Virtualization::Vm,
+            Virtualization::Any => detected != virt::Virtualization::None,
+        };
+        if !met {
+            let expected = match expected {
+                Virtualization::None => "none",
+                Virtualization::Container => "container",
+                Virtualization::Vm => "vm",
+                Virtualization::Any => "any",
+            };
+            return Err(unmet("virtualization", expected));
+        }
+    }
+
+    if !arch_matches(&conditions.architecture, std::env::consts::ARCH) {
+        return Err(unmet("architecture", &conditions.architecture.join(", ")));
+    }
+
+    for (key, value) in &conditions.environment {
+        if std::env::var(key).ok().as_ref() != Some(value) {
+            return Err(unmet(&format!("environment.{key}"), value));
+        }
+    }
+
+    Ok(())
+}
+
+/// Splits the `!` prefix from a condition entry, returning whether the entry is negated and the rest of the entry.
+fn negated(entry: &str) -> (bool, &str) {
+    match entry.strip_prefix('!') {
+        Some(x) => (true, x),
+        None => (false, entry),
+    }
+}
+
+/// Returns `true` if the kernel command-line contains the token. A token without `=` matches both `token` and `token=value`,
+/// while a token with `=` only matches exactly.
+fn cmdline_contains(cmdline: &str, token: &str) -> bool {
+    cmdline.split_ascii_whitespace().any(|x| {
+        x == token || (!token.contains('=') && x.split_once('=').is_some_and(|x| x.0 == token))
+    })
+}
+
+/// Returns `true` if the architecture matches the entries.
+///
+/// Architectures are matched as a whole: the architecture must be one of non-negated entries if there are any, and must not be
+/// any of negated entries.
+fn arch_matches(entries: &[String], arch: &str) -> bool {
+    let (excluded, included): (Vec<_>, Vec<_>) =
+        entries.iter().map(|x| negated(x)).partition(|x| x.0);
+    (included.is_empty() || included.iter().any(|x| x.1 == arch))
+        && !excluded.iter().any(|x| x.1 == arch)
+}
+
+#[cfg(test)]
+mod tests {
+    use airup_sdk::files::service::Conditions;
+
+    #[tokio::test]
+    async fn check() {
+        let dir = std::env::temp_dir();
+        let missing = dir.join(format!("airupd-test-missing-{}", std::process::id()));
+        let (dir, missing) = (dir.display().to_string(), missing.display().to_string());
+
+        let conditions = |path_exists: &[&str], path_is_directory: &[&str]| Conditions {
+            path_exists: path_exists.iter().map(|x| x.to_string()).collect(),
+            path_is_directory: path_is_directory.iter().map(|x| x.to_string()).collect(),
+            ..Default::default()
+        };
+        assert!(super::check(&conditions(&[&dir], &[&dir])).await.is_ok());
+        assert!(
+            super::check(&conditions(&[&format!("!{missing}")], &[]))
+                .await
+                .is_ok()
+        );
+        assert_eq!(
+            super::check(&conditions(&[&missing], &[])).await,
+            Err(format!(
+                "condition `path-exists = \"{missing}\"` is not met"
+            ))
+        );
+        assert!(
+            super::check(&conditions(&[], &[&format!("!{dir}")]))
+                .await
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn negated() {
+        assert_eq!(super::negated("!/a"), (true, "/a"));
+        assert_eq!(super::negated("/a"), (false, "/a"));
+    }
+
+    #[test]
+    fn cmdline_contains() {
+        let cmdline = "root=/dev/sda1 quiet console=ttyS0 single\n";
+        assert!(super::cmdline_contains(cmdline, "quiet"));
+        assert!(super::cmdline_contains(cmdline, "single"));
+        assert!(super::cmdline_contains(cmdline, "console"));
+        assert!(super::cmdline_contains(cmdline, "console=ttyS0"));
+        assert!(!super::cmdline_contains(cmdline, "console=tty0"));
+        assert!(!super::cmdline_contains(cmdline, "roo"));
+        assert!(!super::cmdline_contains(cmdline, "quiet=1"));
+    }
+
+    #[test]
+    fn arch_matches() {
+        let entries = |x: &[&str]| x.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+        assert!(super::arch_matches(&[], "x86_64"));
+        assert!(super::arch_matches(
+            &entries(&["x86_64", "aarch64"]),
+            "x86_64"
+        ));
+        assert!(!super::arch_matches(&entries(&["aarch64"]), "x86_64"));
+        assert!(super::arch_matches(&entries(&["!aarch64"]), "x86_64"));
+        assert!(!super::arch_matches(&entries(&["!x86_64"]), "x86_64"));
+        assert!(!super::arch_matches(
+            &entries(&["x86_64", "!x86_64"]),
+            "x86_64"
+        ));
+    }
+}
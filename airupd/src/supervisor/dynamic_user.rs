@@ -0,0 +1,27 @@
+//! Allocation of transient users for services with `env.dynamic_user` enabled.
+
+use std::{collections::HashSet, sync::Mutex};
+
+/// An allocator of dynamic users.
+///
+/// A dynamic user always has a GID equal to its UID.
+#[derive(Debug, Default)]
+pub struct DynamicUsers {
+    allocated: Mutex<HashSet<u32>>,
+}
+impl DynamicUsers {
+    /// Allocates an ID in the given inclusive range, which is neither allocated nor used by an existing user or group. Returns
+    /// `None` if the range is exhausted.
+    pub fn allocate(&self, (first, last): (u32, u32)) -> Option<u32> {
+        let mut allocated = self.allocated.lock().unwrap();
+        let id =
+            (first..=last).find(|x| !allocated.contains(x) && !airupfx::env::is_id_used(*x))?;
+        allocated.insert(id);
+        Some(id)
+    }
+
+    /// Releases an allocated ID.
+    pub fn release(&self, id: u32) {
+        self.allocated.lock().unwrap().remove(&id);
+    }
+}
@@ -0,0 +1,304 @@
+//! Jobs of the Airup supervisor.
+//!
+//! A job is a user request to operate a service, e.g. starting or stopping it. If the service's supervisor is busy when a job
+//! is submitted, the job is queued and executed after the running task completes, instead of failing immediately.
+
+use super::task::{TaskFeedback, TaskHandle};
+use crate::app::airupd;
+use airup_sdk::{
+    Error,
+    system::{JobKind, JobState, RestartMode},
+};
+use airupfx::prelude::*;
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::{
+        Arc, Mutex,
+        atomic::{self, AtomicBool, AtomicU64},
+    },
+};
+use tokio::sync::watch;
+
+/// Number of finished jobs whose results are kept for [`Jobs::wait`].
+const FINISHED_JOBS_KEPT: usize = 64;
+
+/// A job.
+#[derive(Debug)]
+pub struct Job {
+    id: u64,
+    service: String,
+    kind: Mutex<JobKind>,
+    running: AtomicBool,
+    done: watch::Sender<Option<Result<TaskFeedback, Error>>>,
+}
+impl Job {
+    /// Returns ID of the job.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Returns name of the service that the job operates.
+    pub fn service(&self) -> &str {
+        &self.service
+    }
+
+    /// Returns kind of the job.
+    pub fn kind(&self) -> JobKind {
+        *self.kind.lock().unwrap()
+    }
+
+    /// Changes kind of the job, which is used when merging jobs.
+    pub fn set_kind(&self, kind: JobKind) {
+        *self.kind.lock().unwrap() = kind;
+    }
+
+    /// Returns `true` if the job has finished.
+    pub fn is_finished(&self) -> bool {
+        self.done.borrow().is_some()
+    }
+
+    /// Returns information about the job.
+    pub fn info(&self) -> airup_sdk::system::Job {
+        airup_sdk::system::Job {
+            id: self.id,
+            service: self.service.clone(),
+            kind: self.kind(),
+            state: match self.running.load(atomic::Ordering::Acquire) {
+                true => JobState::Running,
+                false => JobState::Waiting,
+            },
+        }
+    }
+
+    /// Marks the job running the given task. The job finishes when the task completes.
+    pub fn run(self: &Arc<Self>, task: Arc<dyn TaskHandle>) {
+        self.running.store(true, atomic::Ordering::Release);
+        let job = Arc::clone(self);
+        tokio::spawn(async move {
+            let result = task.wait().await;
+            job.finish(result);
+        });
+    }
+
+    /// Finishes the job with the given result.
+    pub fn finish(&self, result: Result<TaskFeedback, Error>) {
+        airupd()
+            .supervisors
+            .jobs
+            .complete(self.id, result.clone().map(|_| ()));
+        self.done.send_replace(Some(result));
+    }
+}
+impl TaskHandle for Job {
+    fn task_class(&self) -> &'static str {
+        match self.kind() {
+            JobKind::Start => "StartService",
+            JobKind::Stop => "StopService",
+            JobKind::Reload => "ReloadService",
+            JobKind::Restart(_) => "RestartService",
+        }
+    }
+
+    fn is_important(&self) -> bool {
+        true
+    }
+
+    fn send_interrupt(&self) {
+        let id = self.id;
+        tokio::spawn(async move {
+            _ = airupd().cancel_job(id).await;
+        });
+    }
+
+    fn wait(&self) -> BoxFuture<'_, Result<TaskFeedback, Error>> {
+        Box::pin(async {
+            let mut receiver = self.done.subscribe();
+            let x = receiver
+                .wait_for(|x| x.is_some())
+                .await
+                .map_err(|_| Error::TaskInterrupted)?;
+
+            x.as_ref()
+                .expect("`watch::Receiver::wait_for` should only return expected value")
+                .clone()
+        })
+    }
+}
+
+/// Registry of jobs, which also keeps results of recently finished jobs.
+#[derive(Debug, Default)]
+pub struct Jobs {
+    next_id: AtomicU64,
+    active: Mutex<BTreeMap<u64, Arc<Job>>>,
+    finished: Mutex<VecDeque<(u64, Result<(), Error>)>>,
+}
+impl Jobs {
+    /// Creates a new job for the given service.
+    pub fn create(&self, service: &str, kind: JobKind) -> Arc<Job> {
+        let job = Arc::new(Job {
+            id: self.next_id.fetch_add(1, atomic::Ordering::AcqRel) + 1,
+            service: service.into(),
+            kind: kind.into(),
+            running: AtomicBool::new(false),
+            done: watch::channel(None).0,
+        });
+        self.active.lock().unwrap().insert(job.id, Arc::clone(&job));
+        job
+    }
+
+    /// Gets an unfinished job by ID.
+    pub fn get(&self, id: u64) -> Option<Arc<Job>> {
+        self.active.lock().unwrap().get(&id).cloned()
+    }
+
+    /// Lists all unfinished jobs, in order of submission.
+    pub fn list(&self) -> Vec<airup_sdk::system::Job> {
+        self.active
+            .lock()
+            .unwrap()
+            .values()
+            .map(|x| x.info())
+            .collect()
+    }
+
+    /// Waits for the specified job to finish, returning its result.
+    ///
+    /// # Errors
+    /// Besides errors of the job itself, this returns [`Error::NotFound`] if the job does not exist, or it has finished too long
+    /// ago.
+    pub async fn wait(&self, id: u64) -> Result<(), Error> {
+        if let Some(job) = self.get(id) {
+            return job.wait().await.map(|_| ());
+        }
+
+        self.finished
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|x| x.0 == id)
+            .map(|x| x.1.clone())
+            .unwrap_or(Err(Error::NotFound))
+    }
+
+    /// Moves a job from the active set to the finished set.
+    fn complete(&self, id: u64, result: Result<(), Error>) {
+        if self.active.lock().unwrap().remove(&id).is_none() {
+            return;
+        }
+        let mut finished = self.finished.lock().unwrap();
+        if finished.len() >= FINISHED_JOBS_KEPT {
+            finished.pop_front();
+        }
+        finished.push_back((id, result));
+    }
+}
+
+/// Queue of jobs of a supervisor.
+#[derive(Debug, Default)]
+pub struct JobQueue {
+    /// The job whose task is currently running.
+    pub current: Option<Arc<Job>>,
+
+    /// Jobs waiting for the running task to complete.
+    pub pending: VecDeque<Arc<Job>>,
+}
+impl JobQueue {
+    /// Returns the latest unfinished job, which is the last pending job, or the running job if no job is pending.
+    pub fn latest(&self) -> Option<&Arc<Job>> {
+        self.pending
+            .back()
+            .or_else(|| self.current.as_ref().filter(|x| !x.is_finished()))
+    }
+
+    /// Merges a new job of the given kind with the queue, so that the latest request always wins.
+    ///
+    /// Returns the existing job which the new one is merged into, if any, and pending jobs superseded by the new one. The
+    /// caller is responsible for finishing superseded jobs.
+    pub fn merge(&mut self, kind: JobKind) -> (Option<Arc<Job>>, Vec<Arc<Job>>) {
+        let mut superseded = Vec::new();
+        let merged = match kind {
+            // A start joins the latest start or restart. A start after a pending stop turns the stop into a restart.
+            JobKind::Start => match self.pending.back() {
+                Some(job) if job.kind() == JobKind::Stop => {
+                    job.set_kind(JobKind::Restart(RestartMode::TryRestart));
+                    Some(job)
+                }
+                _ => self
+                    .latest()
+                    .filter(|x| matches!(x.kind(), JobKind::Start | JobKind::Restart(_))),
+            },
+
+            // A stop supersedes pending starts, restarts and reloads, then joins the latest stop.
+            JobKind::Stop => {
+                superseded.extend(
+                    self.pending
+                        .iter()
+                        .filter(|x| x.kind() != JobKind::Stop)
+                        .cloned(),
+                );
+                self.pending.retain(|x| x.kind() == JobKind::Stop);
+                self.latest().filter(|x| x.kind() == JobKind::Stop)
+            }
+
+            _ => self.latest().filter(|x| x.kind() == kind),
+        };
+
+        (merged.cloned(), superseded)
+    }
+
+    /// Removes a pending job by ID.
+    pub fn remove(&mut self, id: u64) -> Option<Arc<Job>> {
+        let index = self.pending.iter().position(|x| x.id() == id)?;
+        self.pending.remove(index)
+    }
+}
+impl Drop for JobQueue {
+    fn drop(&mut self) {
+        for job in self.pending.drain(..) {
+            job.finish(Err(Error::TaskInterrupted));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{JobQueue, Jobs};
+    use airup_sdk::system::{JobKind, RestartMode};
+
+    #[test]
+    fn merge() {
+        let jobs = Jobs::default();
+        let submit = |queue: &mut JobQueue, kind| {
+            let (merged, superseded) = queue.merge(kind);
+            if merged.is_none() {
+                queue.pending.push_back(jobs.create("test", kind));
+            }
+            superseded.len()
+        };
+
+        // start -> stop -> start: the queued stop becomes a restart, so the service ends started
+        let mut queue = JobQueue::default();
+        queue.current = Some(jobs.create("test", JobKind::Start));
+        assert_eq!(submit(&mut queue, JobKind::Stop), 0);
+        assert_eq!(submit(&mut queue, JobKind::Start), 0);
+        let kinds: Vec<_> = queue.pending.iter().map(|x| x.kind()).collect();
+        assert_eq!(kinds, [JobKind::Restart(RestartMode::TryRestart)]);
+        queue.pending.clear();
+
+        // stop -> start -> stop: the queued start is superseded, and the new stop joins the running one
+        let mut queue = JobQueue::default();
+        queue.current = Some(jobs.create("test", JobKind::Stop));
+        assert_eq!(submit(&mut queue, JobKind::Start), 0);
+        assert_eq!(queue.pending.len(), 1);
+        assert_eq!(submit(&mut queue, JobKind::Stop), 1);
+        assert!(queue.pending.is_empty());
+        assert_eq!(queue.latest().map(|x| x.kind()), Some(JobKind::Stop));
+
+        // Repeated requests of the same kind are merged
+        let mut queue = JobQueue::default();
+        submit(&mut queue, JobKind::Reload);
+        submit(&mut queue, JobKind::Reload);
+        assert_eq!(queue.pending.len(), 1);
+        queue.pending.clear();
+    }
+}
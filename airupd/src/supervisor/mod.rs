@@ -1,28 +1,46 @@
 //! # The Airup Supervisor
 //! Main module containing full airup supervisor logic.
 
+mod conditions;
+mod dynamic_user;
+pub mod job;
 pub mod logging;
 pub mod task;
 
 use crate::{ace::Child, app::airupd};
 use airup_sdk::{
     Error,
-    files::{Service, Validate, service::WatchdogKind},
-    system::{Event, QueryService, ServiceStartReason, Status},
+    files::{
+        Service, Validate,
+        service::{KillMode, WatchdogAction, WatchdogKind},
+    },
+    system::{
+        Event, ExitStatus, JobKind, QueryService, ResourceUsage, RestartMode, ServiceProcess,
+        ServiceStartReason, StartTimings, Status, WatchdogStatus,
+    },
+};
+use airupfx::{
+    isolator::Realm,
+    process::{ProcessInfo, Wait},
+    time::Alarm,
 };
-use airupfx::{isolator::Realm, process::Wait, time::Alarm};
+use job::{Job, JobQueue};
 use std::{
     cmp,
     collections::HashMap,
+    os::fd::OwnedFd,
     sync::{
         Arc, Mutex, RwLock,
-        atomic::{self, AtomicBool, AtomicI32, AtomicI64},
+        atomic::{self, AtomicBool, AtomicI32, AtomicI64, AtomicU32, AtomicU64},
     },
-    time::Duration,
+    time::{Duration, Instant},
 };
-use task::{Empty, TaskHandle};
+use task::TaskHandle;
 use tokio::sync::{mpsc, oneshot};
 
+/// Time that processes of a service gathered for queries are cached for.
+const PROCESSES_CACHE_TTL: Duration = Duration::from_secs(1);
+
 macro_rules! supervisor_req {
     ($name:ident, $ret:ty, $req:expr) => {
         pub async fn $name(&self) -> $ret {
@@ -38,6 +56,8 @@ macro_rules! supervisor_req {
 pub struct Manager {
     supervisors: tokio::sync::RwLock<HashMap<String, Arc<SupervisorHandle>>>,
     provided: tokio::sync::RwLock<HashMap<String, Arc<SupervisorHandle>>>,
+    dynamic_users: dynamic_user::DynamicUsers,
+    pub jobs: job::Jobs,
 }
 impl Manager {
     /// Creates a new, empty [`Manager`] instance.
@@ -203,6 +223,7 @@ impl SupervisorHandle {
             context: SupervisorContext::new(service),
             timers: Box::default(),
             events: airupd().events.subscribe(),
+            jobs: JobQueue::default(),
         };
         supervisor.start();
 
@@ -210,10 +231,8 @@ impl SupervisorHandle {
     }
 
     supervisor_req!(query, QueryService, Request::Query);
-    supervisor_req!(start, Result<Arc<dyn TaskHandle>, Error>, Request::Start);
-    supervisor_req!(stop, Result<Arc<dyn TaskHandle>, Error>, Request::Stop);
-    supervisor_req!(kill, Result<(), Error>, Request::Kill);
-    supervisor_req!(reload, Result<Arc<dyn TaskHandle>, Error>, Request::Reload);
+    supervisor_req!(kill, Result<Arc<dyn TaskHandle>, Error>, Request::Kill);
+    supervisor_req!(list_processes, Vec<ServiceProcess>, Request::ListProcesses);
     supervisor_req!(
         interrupt_task,
         Result<Arc<dyn TaskHandle>, Error>,
@@ -229,6 +248,49 @@ impl SupervisorHandle {
         }
     }
 
+    pub async fn submit(&self, kind: JobKind) -> Result<Arc<Job>, Error> {
+        let (tx, rx) = oneshot::channel();
+        self.sender.send(Request::Submit(kind, tx)).await.unwrap();
+        rx.await.unwrap()
+    }
+
+    pub async fn cancel_job(&self, id: u64) -> Result<(), Error> {
+        let (tx, rx) = oneshot::channel();
+        self.sender.send(Request::CancelJob(id, tx)).await.unwrap();
+        rx.await.unwrap()
+    }
+
+    pub async fn exec(
+        &self,
+        command: ExecCommand,
+        stdio: [OwnedFd; 3],
+    ) -> Result<crate::ace::Child, Error> {
+        let (tx, rx) = oneshot::channel();
+        self.sender
+            .send(Request::Exec(command, stdio, tx))
+            .await
+            .unwrap();
+        rx.await.unwrap()
+    }
+
+    pub async fn expand_ace(&self, script: String) -> Result<Vec<Vec<String>>, Error> {
+        let (tx, rx) = oneshot::channel();
+        self.sender
+            .send(Request::ExpandAce(script, tx))
+            .await
+            .unwrap();
+        rx.await.unwrap()
+    }
+
+    pub async fn signal(&self, signum: i32, all: bool) -> Result<(), Error> {
+        let (tx, rx) = oneshot::channel();
+        self.sender
+            .send(Request::Signal(signum, all, tx))
+            .await
+            .unwrap();
+        rx.await.unwrap()
+    }
+
     pub async fn update_manifest(&self, new: Box<Service>) -> Result<Service, Error> {
         let (tx, rx) = oneshot::channel();
         self.sender
@@ -246,6 +308,7 @@ struct Supervisor {
     context: Arc<SupervisorContext>,
     timers: Box<Timers>,
     events: async_broadcast::Receiver<Event>,
+    jobs: JobQueue,
 }
 impl Supervisor {
     /// Starts the supervisor task.
@@ -278,17 +341,26 @@ impl Supervisor {
             Request::Query(chan) => {
                 _ = chan.send(self.query().await);
             }
-            Request::Start(chan) => {
-                _ = chan.send(self.user_start_service().await);
+            Request::Submit(kind, chan) => {
+                _ = chan.send(self.submit_job(kind).await);
             }
-            Request::Stop(chan) => {
-                _ = chan.send(self.user_stop_service(false).await);
+            Request::CancelJob(id, chan) => {
+                _ = chan.send(self.cancel_job(id));
+            }
+            Request::Exec(command, stdio, chan) => {
+                _ = chan.send(self.exec(command, stdio).await);
+            }
+            Request::ExpandAce(script, chan) => {
+                _ = chan.send(self.expand_ace(&script).await);
             }
             Request::Kill(chan) => {
-                _ = chan.send(self.user_stop_service(true).await.map(|_| ()));
+                _ = chan.send(self.user_stop_service(true).await);
             }
-            Request::Reload(chan) => {
-                _ = chan.send(self.reload_service().await);
+            Request::ListProcesses(chan) => {
+                _ = chan.send(self.list_processes().await);
+            }
+            Request::Signal(signum, all, chan) => {
+                _ = chan.send(self.signal_service(signum, all).await);
             }
             Request::UpdateManifest(new, chan) => {
                 _ = chan.send(self.update_manifest(*new).await);
@@ -319,6 +391,7 @@ impl Supervisor {
             if let Some(x) = &mut self.timers.watchdog {
                 x.reset();
             }
+            self.on_health_check_success();
             return;
         }
         if let Some(exec) = self.context.service.event_handlers.get(&event.id) {
@@ -338,6 +411,10 @@ impl Supervisor {
     async fn handle_wait(&mut self, wait: Wait) {
         self.context.status.set(Status::Stopped);
         self.context.set_child(None).await;
+        *self.context.last_exit.lock().unwrap() = Some(match wait.code() {
+            Some(code) => ExitStatus::Exited(code),
+            None => ExitStatus::Signaled(wait.signal().unwrap_or_default()),
+        });
         if self.context.retry.enabled() {
             _ = self.cleanup_service(wait).await;
         }
@@ -347,23 +424,31 @@ impl Supervisor {
     ///
     /// If error auto-saving is enabled, it sets last error to the task's result.
     async fn handle_wait_task(&mut self, handle: Arc<dyn TaskHandle>) {
-        let Err(error) = handle.wait().await else {
-            return;
-        };
+        let result = handle.wait().await;
         if handle.task_class() == "HealthCheck" {
-            self.on_watchdog_failure().await;
+            match result {
+                Ok(_) => self.on_health_check_success(),
+                Err(_) => self.on_health_check_failure().await,
+            }
+        }
+        if handle.task_class() == "RestartService" && self.context.status.get() == Status::Active {
+            // The watchdog timer may be disabled while the service is stopped during restarting.
+            self.timers.on_start();
         }
-        if self.context.last_error.take_autosave() {
+        if let Err(error) = result
+            && self.context.last_error.take_autosave()
+        {
             self.context.last_error.set(error);
         }
+        self.run_pending_jobs().await;
     }
 
     /// Called when the watchdog timer goes off.
     async fn handle_watchdog(&mut self) {
         if self.context.status.get() == Status::Active {
             match self.context.service.watchdog.kind {
-                Some(WatchdogKind::HealthCheck) => _ = self.health_check().await,
-                Some(WatchdogKind::Notify) => _ = self.on_watchdog_failure().await,
+                Some(WatchdogKind::Notify) => self.on_health_check_failure().await,
+                Some(_) => _ = self.health_check().await,
                 None => (),
             };
         } else if let Some(alarm) = &mut self.timers.watchdog {
@@ -374,6 +459,7 @@ impl Supervisor {
     /// Queries information about the supervisor.
     async fn query(&self) -> QueryService {
         let task = self.current_task.0.as_ref();
+        let processes = self.context.counted_processes().await;
         QueryService {
             status: self.context.status.get(),
             status_since: Some(self.context.status.timestamp()),
@@ -383,11 +469,26 @@ impl Supervisor {
                 .realm
                 .as_ref()
                 .and_then(|x| x.memory_usage().ok())
-                .map(|x| x as u64),
+                .map(|x| x as u64)
+                .or_else(|| {
+                    (!processes.is_empty()).then(|| processes.iter().map(|x| x.memory).sum())
+                }),
+            resource_usage: resource_usage(self.context.realm.as_deref(), &processes),
             task_class: task.map(|x| x.task_class().to_owned()),
             last_error: self.context.last_error.get(),
             definition: self.context.service.clone(),
             start_reason: self.context.start_reason.read().unwrap().clone(),
+            skipped: self.context.skipped.lock().unwrap().clone(),
+            watchdog: self
+                .context
+                .service
+                .watchdog
+                .kind
+                .map(|_| self.context.watchdog.status()),
+            restart_count: self.context.restarts.count(),
+            last_restart: self.context.restarts.last(),
+            last_exit: *self.context.last_exit.lock().unwrap(),
+            start_timings: self.context.start_timings.lock().unwrap().clone(),
         }
     }
 
@@ -418,6 +519,95 @@ impl Supervisor {
         Ok(task_handle)
     }
 
+    /// Submits a job to the supervisor.
+    ///
+    /// If an unfinished job can be merged with the new one, the existing job is returned. Otherwise, the job is executed
+    /// immediately if the supervisor is idle, or queued if a task is running.
+    async fn submit_job(&mut self, kind: JobKind) -> Result<Arc<Job>, Error> {
+        let (merged, superseded) = self.jobs.merge(kind);
+        for job in superseded {
+            job.finish(Err(Error::TaskInterrupted));
+        }
+        if let Some(job) = merged {
+            return Ok(job);
+        }
+
+        let job = airupd()
+            .supervisors
+            .jobs
+            .create(&self.context.service.name, kind);
+        if self.jobs.pending.is_empty() {
+            match self.run_job(&job).await {
+                Ok(()) => return Ok(job),
+                Err(Error::TaskExists) => {}
+                Err(err) => {
+                    job.finish(Err(err.clone()));
+                    return Err(err);
+                }
+            }
+        }
+        self.jobs.pending.push_back(Arc::clone(&job));
+
+        Ok(job)
+    }
+
+    /// Executes a job.
+    async fn run_job(&mut self, job: &Arc<Job>) -> Result<(), Error> {
+        let task = match job.kind() {
+            JobKind::Start => self.user_start_service().await?,
+            JobKind::Stop => self.user_stop_service(false).await?,
+            JobKind::Reload => self.reload_service().await?,
+            JobKind::Restart(mode) => self.user_restart_service(mode).await?,
+        };
+        job.run(task);
+        self.jobs.current = Some(Arc::clone(job));
+
+        Ok(())
+    }
+
+    /// Executes pending jobs until a task is running or the queue is empty.
+    async fn run_pending_jobs(&mut self) {
+        while !self.current_task.has_task() {
+            let Some(job) = self.jobs.pending.pop_front() else {
+                break;
+            };
+            if let Err(err) = self.run_job(&job).await {
+                job.finish(Err(err));
+            }
+        }
+    }
+
+    /// Cancels a job. A pending job is removed from the queue, and a running job has its task interrupted.
+    fn cancel_job(&mut self, id: u64) -> Result<(), Error> {
+        if let Some(job) = self.jobs.remove(id) {
+            job.finish(Err(Error::TaskInterrupted));
+            return Ok(());
+        }
+
+        match &self.jobs.current {
+            Some(job) if job.id() == id && !job.is_finished() => {
+                self.current_task.interrupt()?;
+                self.context.last_error.set_autosave(false);
+                Ok(())
+            }
+            _ => Err(Error::NotFound),
+        }
+    }
+
+    /// Spawns a command in the execution environment of the service, with the given standard I/O file descriptors.
+    async fn exec(
+        &mut self,
+        command: ExecCommand,
+        stdio: [OwnedFd; 3],
+    ) -> Result<crate::ace::Child, Error> {
+        exec_in(&self.context, command, stdio).await
+    }
+
+    /// Expands an ACE script in the execution environment of the service, without running it.
+    async fn expand_ace(&self, script: &str) -> Result<Vec<Vec<String>>, Error> {
+        expand_ace_in(&self.context, script).await
+    }
+
     /// Called when the user attempted to start the service.
     ///
     /// This resets the retry counter, then returns the just-started "StartService" task if task creation succeeded.
@@ -443,9 +633,24 @@ impl Supervisor {
         }
     }
 
+    /// Called when the user attempted to restart the service.
+    ///
+    /// This resets the retry counter, then returns the just-started "RestartService" task if task creation succeeded.
+    async fn user_restart_service(
+        &mut self,
+        mode: RestartMode,
+    ) -> Result<Arc<dyn TaskHandle>, Error> {
+        self.current_task.interrupt_non_important().await;
+        self.context.retry.reset();
+        let task_handle = self.restart_service(mode).await?;
+        *self.context.start_reason.write().unwrap() = Some(ServiceStartReason::Manual);
+        Ok(task_handle)
+    }
+
     /// Starts the service.
     async fn start_service(&mut self) -> Result<Arc<dyn TaskHandle>, Error> {
         self.timers.on_start();
+        self.context.watchdog.reset();
         self.current_task
             .start_task(&self.context, async {
                 task::start::start(Arc::clone(&self.context))
@@ -462,30 +667,76 @@ impl Supervisor {
             .await
     }
 
-    /// Forces the service to stop.
+    /// Forces the service to stop, returns a handle of the `KillService` task on success.
+    ///
+    /// This sends the service's kill signal to its processes selected by the kill mode. If the kill signal is `SIGKILL`, or
+    /// they did not exit in [`crate::ace::KILL_SIGNAL_TIMEOUT`] after receiving it, the whole realm is killed if possible,
+    /// regardless of the kill mode.
     async fn kill_service(&mut self) -> Result<Arc<dyn TaskHandle>, Error> {
-        let child = self.context.child.read().await;
-
-        if let Some(realm) = &self.context.realm {
-            if realm.kill().is_ok() {
-                return Ok(Arc::new(Empty));
-            }
-        }
-
-        if let Some(ch) = &*child {
-            ch.kill().await?;
-            Ok(Arc::new(Empty))
-        } else {
-            Err(Error::unsupported(
+        if self.context.pid().await.is_none() {
+            return Err(Error::unsupported(
                 "cannot kill a service without a process",
-            ))
+            ));
         }
+        Ok(task::stop::kill(Arc::clone(&self.context)))
+    }
+
+    /// Lists processes belonging to the service.
+    async fn list_processes(&self) -> Vec<ServiceProcess> {
+        self.context
+            .processes()
+            .await
+            .into_iter()
+            .map(|x| ServiceProcess {
+                pid: x.pid,
+                ppid: x.ppid,
+                uid: x.uid,
+                name: x.name,
+                cmdline: x.cmdline,
+                start_time: x.start_time,
+            })
+            .collect()
+    }
+
+    /// Sends a signal to the service.
+    ///
+    /// If `all` is `true`, the signal is sent to all processes belonging to the service. If the service's processes cannot be
+    /// listed, or `all` is `false`, the signal is sent to the main process only.
+    async fn signal_service(&self, signum: i32, all: bool) -> Result<(), Error> {
+        let child = self.context.child.read().await;
+        let Some(child) = &*child else {
+            return Err(Error::unsupported(
+                "cannot signal a service without a process",
+            ));
+        };
+        let mode = match all {
+            true => KillMode::Realm,
+            false => KillMode::Main,
+        };
+
+        KillTarget::select(self.context.realm.as_deref(), child.id(), mode)
+            .send(child, signum)
+            .await
     }
 
     /// Reloads the service.
     async fn reload_service(&mut self) -> Result<Arc<dyn TaskHandle>, Error> {
         self.current_task
-            .start_task(&self.context, task::reload::start(&self.context))
+            .start_task(
+                &self.context,
+                task::reload::start(Arc::clone(&self.context)),
+            )
+            .await
+    }
+
+    /// Restarts the service.
+    async fn restart_service(&mut self, mode: RestartMode) -> Result<Arc<dyn TaskHandle>, Error> {
+        self.timers.on_start();
+        self.context.watchdog.reset();
+        self.current_task
+            .start_task(&self.context, async {
+                task::restart::start(Arc::clone(&self.context), mode)
+            })
             .await
     }
 
@@ -507,9 +758,58 @@ impl Supervisor {
             .await
     }
 
+    /// Called when a health check succeeded.
+    fn on_health_check_success(&mut self) {
+        let threshold = self.context.service.watchdog.success_threshold();
+        self.context.watchdog.mark_success(threshold);
+    }
+
+    /// Called when a health check failed. If the number of consecutive failures reaches the threshold, the watchdog barks.
+    async fn on_health_check_failure(&mut self) {
+        let threshold = self.context.service.watchdog.failure_threshold();
+        if self.context.watchdog.mark_failure(threshold) {
+            self.on_watchdog_failure().await;
+        }
+    }
+
+    /// Called when the service is considered unhealthy, which takes the configured watchdog action.
     async fn on_watchdog_failure(&mut self) {
-        self.context.last_error.set(Error::Watchdog);
-        _ = self.stop_service().await;
+        let action = self.context.service.watchdog.action;
+        if action != WatchdogAction::Stop {
+            self.context.watchdog.set_degraded();
+        }
+        match action {
+            WatchdogAction::Stop => {
+                self.context.last_error.set(Error::Watchdog);
+                _ = self.stop_service().await;
+            }
+            WatchdogAction::Restart => {
+                _ = self.restart_service(RestartMode::Restart).await;
+                // Restarting the service resets the watchdog status, so it is marked degraded again
+                self.context.watchdog.set_degraded();
+            }
+            WatchdogAction::Remediate => {
+                let Some(command) = self.context.service.watchdog.remediate.clone() else {
+                    return;
+                };
+                let Ok(ace) = task::ace(&self.context).await else {
+                    return;
+                };
+                let timeout = self.context.service.exec.health_check_timeout();
+                tokio::spawn(async move {
+                    _ = ace.run_wait_timeout(&command, timeout).await;
+                });
+            }
+            WatchdogAction::Event => {
+                let event = Event::new(
+                    "service_unhealthy".into(),
+                    self.context.service.name.clone(),
+                );
+                // The event bus is drained by this supervisor itself, so the event must not be awaited here
+                tokio::spawn(airupd().events.trigger(event));
+            }
+            WatchdogAction::Degraded => {}
+        }
     }
 }
 
@@ -583,13 +883,31 @@ struct SupervisorContext {
     realm: Option<Arc<Realm>>,
     child: tokio::sync::RwLock<Option<Child>>,
     retry: RetryContext,
+    watchdog: WatchdogContext,
+    restarts: RestartHistory,
     start_reason: RwLock<Option<ServiceStartReason>>,
+    dynamic_user: Mutex<Option<u32>>,
+    skipped: Mutex<Option<String>>,
+    last_exit: Mutex<Option<ExitStatus>>,
+    start_timings: Mutex<Option<StartTimings>>,
+    processes_cache: Mutex<Option<ProcessesCache>>,
 }
 impl SupervisorContext {
     /// Creates a new [`SupervisorContext`] instance for the given [`Service`].
     fn new(service: Service) -> Arc<Self> {
         let realm = Realm::new().ok().map(Arc::new);
         setup_realm(&realm, &service);
+        Self::with_realm(service, realm)
+    }
+
+    /// Creates a new [`SupervisorContext`] instance for the given [`Service`], which is not associated with any realm.
+    ///
+    /// This is used to build the execution environment of a service which is not supervised, without leaving a realm behind.
+    fn detached(service: Service) -> Arc<Self> {
+        Self::with_realm(service, None)
+    }
+
+    fn with_realm(service: Service, realm: Option<Arc<Realm>>) -> Arc<Self> {
         Arc::new(Self {
             service,
             last_error: Default::default(),
@@ -597,21 +915,189 @@ impl SupervisorContext {
             realm,
             child: Default::default(),
             retry: Default::default(),
+            watchdog: Default::default(),
+            restarts: Default::default(),
             start_reason: Default::default(),
+            dynamic_user: Default::default(),
+            skipped: Default::default(),
+            last_exit: Default::default(),
+            start_timings: Default::default(),
+            processes_cache: Default::default(),
         })
     }
 
+    /// Updates timings of the current start of the service.
+    fn record_timing(&self, f: impl FnOnce(&mut StartTimings, i64)) {
+        if let Some(x) = &mut *self.start_timings.lock().unwrap() {
+            f(x, airupfx::time::timestamp_ms());
+        }
+    }
+
     /// Returns main PID of the service supervised by the supervisor.
     async fn pid(&self) -> Option<i64> {
         self.child.read().await.as_ref().map(|x| x.id())
     }
 
+    /// Returns information about processes belonging to the service.
+    async fn processes(&self) -> Vec<ProcessInfo> {
+        processes(self.realm.as_deref(), self.pid().await)
+    }
+
+    /// Returns information about processes that are counted in resource usage statistics of the service.
+    ///
+    /// This is the same as [`Self::processes`], but since it is called on every query, the result is cached for
+    /// [`PROCESSES_CACHE_TTL`], so frequent queries do not walk the process table each time if the realm cannot list its
+    /// processes.
+    async fn counted_processes(&self) -> Vec<ProcessInfo> {
+        let pid = self.pid().await;
+        if let Some(cache) = &*self.processes_cache.lock().unwrap()
+            && cache.pid == pid
+            && cache.timestamp.elapsed() < PROCESSES_CACHE_TTL
+        {
+            return cache.processes.clone();
+        }
+
+        let processes = processes(self.realm.as_deref(), pid);
+        *self.processes_cache.lock().unwrap() = Some(ProcessesCache {
+            pid,
+            timestamp: Instant::now(),
+            processes: processes.clone(),
+        });
+        processes
+    }
+
+    /// Returns the dynamic user allocated to the service, if any.
+    fn dynamic_user(&self) -> Option<u32> {
+        *self.dynamic_user.lock().unwrap()
+    }
+
+    /// Allocates a dynamic user for the service, or returns the already allocated one.
+    fn allocate_dynamic_user(&self) -> Result<u32, Error> {
+        let mut lock = self.dynamic_user.lock().unwrap();
+        if let Some(id) = *lock {
+            return Ok(id);
+        }
+        let range = airupd()
+            .storage
+            .config
+            .system_conf
+            .system
+            .dynamic_user_range;
+        let id = airupd()
+            .supervisors
+            .dynamic_users
+            .allocate(range)
+            .ok_or_else(|| Error::internal("no dynamic user is available"))?;
+        *lock = Some(id);
+        Ok(id)
+    }
+
+    /// Releases the dynamic user allocated to the service, if any.
+    fn release_dynamic_user(&self) {
+        if let Some(id) = self.dynamic_user.lock().unwrap().take() {
+            airupd().supervisors.dynamic_users.release(id);
+        }
+    }
+
     /// Sets new child for the supervisor.
     async fn set_child<T: Into<Option<Child>>>(&self, new: T) -> Option<Child> {
         std::mem::replace(&mut *self.child.write().await, new.into())
     }
 }
 
+/// Processes of a service that a signal is sent to, selected by a [`KillMode`].
+#[derive(Debug)]
+enum KillTarget {
+    /// The main process of the service.
+    Main,
+
+    /// The process group with the given ID.
+    Group(i64),
+
+    /// Processes with the given IDs.
+    Processes(Vec<i64>),
+}
+impl KillTarget {
+    /// Selects processes of a service with the given main PID by the given [`KillMode`].
+    ///
+    /// If processes of the kill mode cannot be determined, this falls back to [`KillTarget::Main`].
+    fn select(realm: Option<&Realm>, pid: i64, mode: KillMode) -> Self {
+        match mode {
+            KillMode::Main => Self::Main,
+            KillMode::Group => match airupfx::process::process(pid) {
+                // Signaling a process group that the main process does not lead may hit processes outside the service, even
+                // including `airupd` itself.
+                Ok(info) if info.pgid == pid => Self::Group(pid),
+                _ => Self::Main,
+            },
+            KillMode::Realm => {
+                let pids: Vec<_> = processes(realm, Some(pid))
+                    .into_iter()
+                    .map(|x| x.pid)
+                    .collect();
+                match pids.is_empty() {
+                    true => Self::Main,
+                    false => Self::Processes(pids),
+                }
+            }
+        }
+    }
+
+    /// Sends a signal to the target. Processes that have already exited are ignored.
+    async fn send(&self, child: &Child, signum: i32) -> Result<(), Error> {
+        let kill = |pid: i64| match airupfx::process::kill(pid, signum) {
+            Err(err) if err.raw_os_error() != Some(libc::ESRCH) => Err(Error::Io {
+                message: err.to_string(),
+            }),
+            _ => Ok(()),
+        };
+
+        match self {
+            Self::Main => child.send_signal(signum).await?,
+            Self::Group(pgid) => kill(-pgid)?,
+            Self::Processes(pids) => pids.iter().try_for_each(|&x| kill(x))?,
+        }
+
+        Ok(())
+    }
+
+    /// Returns `true` if any process other than the main process in the target is still alive.
+    fn is_alive(&self) -> bool {
+        match self {
+            Self::Main => false,
+            Self::Group(pgid) => airupfx::process::kill(-pgid, 0).is_ok(),
+            Self::Processes(pids) => pids.iter().any(|&x| airupfx::process::kill(x, 0).is_ok()),
+        }
+    }
+
+    /// Waits until no process other than the main process in the target is alive. Returns `false` if the timeout expired.
+    async fn wait_timeout(&self, timeout: Option<Duration>) -> bool {
+        self.poll_timeout(timeout, || self.is_alive()).await
+    }
+
+    /// Waits until neither the main process with the given PID nor any other process in the target exists. Returns
+    /// `Ok(false)` if the timeout expired.
+    ///
+    /// Unlike [`KillTarget::wait_timeout`], this does not rely on the main process being waited by the caller, but a main
+    /// process that has exited is only considered gone once it is reaped.
+    async fn wait_exited(&self, pid: i64, timeout: Option<Duration>) -> Result<bool, Error> {
+        let alive = || airupfx::process::kill(pid, 0).is_ok() || self.is_alive();
+        Ok(self.poll_timeout(timeout, alive).await)
+    }
+
+    /// Polls until `alive` returns `false`. Returns `false` if the timeout expired.
+    async fn poll_timeout(&self, timeout: Option<Duration>, alive: impl Fn() -> bool) -> bool {
+        let countdown = airupfx::time::countdown(timeout);
+        while alive() {
+            if countdown.left().is_some_and(|x| x.is_zero()) {
+                return false;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        true
+    }
+}
+
 #[derive(Debug, Default)]
 struct StatusContext {
     data: Mutex<Status>,
@@ -728,6 +1214,87 @@ impl RetryContext {
     }
 }
 
+/// Context type that stores service watchdog status.
+#[derive(Debug, Default)]
+struct WatchdogContext {
+    degraded: AtomicBool,
+    failures: AtomicU32,
+    successes: AtomicU32,
+}
+impl WatchdogContext {
+    /// Records a failed health check. Returns `true` each time the number of consecutive failures reaches a multiple of the
+    /// threshold.
+    fn mark_failure(&self, threshold: u32) -> bool {
+        self.successes.store(0, atomic::Ordering::Release);
+        let failures = self.failures.fetch_add(1, atomic::Ordering::AcqRel) + 1;
+        failures.is_multiple_of(threshold)
+    }
+
+    /// Records a successful health check. The degraded state is cleared if the number of consecutive successes reaches the
+    /// threshold.
+    fn mark_success(&self, threshold: u32) {
+        self.failures.store(0, atomic::Ordering::Release);
+        let successes = self.successes.fetch_add(1, atomic::Ordering::AcqRel) + 1;
+        if successes >= threshold {
+            self.degraded.store(false, atomic::Ordering::Release);
+        }
+    }
+
+    /// Marks the service degraded.
+    fn set_degraded(&self) {
+        self.degraded.store(true, atomic::Ordering::Release);
+    }
+
+    /// Resets the watchdog status.
+    fn reset(&self) {
+        self.degraded.store(false, atomic::Ordering::Release);
+        self.failures.store(0, atomic::Ordering::Release);
+        self.successes.store(0, atomic::Ordering::Release);
+    }
+
+    /// Returns current status of the watchdog.
+    fn status(&self) -> WatchdogStatus {
+        WatchdogStatus {
+            degraded: self.degraded.load(atomic::Ordering::Acquire),
+            consecutive_failures: self.failures.load(atomic::Ordering::Acquire),
+            consecutive_successes: self.successes.load(atomic::Ordering::Acquire),
+        }
+    }
+}
+
+/// Context type that stores restart history of a service.
+#[derive(Debug, Default)]
+struct RestartHistory {
+    count: AtomicU64,
+    last: AtomicI64,
+}
+impl RestartHistory {
+    /// Records that the service is restarted.
+    fn mark(&self) {
+        self.count.fetch_add(1, atomic::Ordering::AcqRel);
+        self.last
+            .store(airupfx::time::timestamp_ms(), atomic::Ordering::Release);
+    }
+
+    /// Returns number of times the service has been restarted.
+    fn count(&self) -> u64 {
+        self.count.load(atomic::Ordering::Acquire)
+    }
+
+    /// Returns the time when the service was last restarted.
+    fn last(&self) -> Option<i64> {
+        (self.count() != 0).then(|| self.last.load(atomic::Ordering::Acquire))
+    }
+}
+
+/// Processes of a service gathered by [`SupervisorContext::counted_processes`], cached to be reused by later queries.
+#[derive(Debug)]
+struct ProcessesCache {
+    pid: Option<i64>,
+    timestamp: Instant,
+    processes: Vec<ProcessInfo>,
+}
+
 /// A structure that provides a collection of supervisor timers.
 #[derive(Debug, Default)]
 struct Timers {
@@ -788,24 +1355,66 @@ impl crate::app::Airupd {
         supervisor.autostart(why).await
     }
 
-    /// Starts the specific service, returns a handle of the spawned `StartService` task on success.
+    /// Submits a job to the specific service's supervisor, returns the job on success.
+    ///
+    /// If the supervisor is busy, the job is queued instead of failing. If an unfinished job of the service can be merged with
+    /// the new one, the existing job is returned.
     ///
     /// # Errors
-    /// This method would fail if the service is already active, having another running task or the specific service was not
-    /// found.
-    pub async fn start_service(&self, name: &str) -> Result<Arc<dyn TaskHandle>, Error> {
+    /// This method would fail if the specific service was not found, or the job cannot be executed, e.g. starting an active
+    /// service when the supervisor is idle.
+    pub async fn submit_job(&self, name: &str, kind: JobKind) -> Result<Arc<Job>, Error> {
         match self.supervisors.get(name).await {
-            Some(supervisor) => Ok(supervisor.start().await?),
+            Some(supervisor) => supervisor.submit(kind).await,
+            None if matches!(kind, JobKind::Stop | JobKind::Reload) => {
+                self.storage.get_service_patched(name).await?;
+                Err(Error::NotStarted)
+            }
             None => {
                 let supervisor = self
                     .supervisors
                     .supervise(self.storage.get_service_patched(name).await?)
                     .await;
-                Ok(supervisor.start().await?)
+                supervisor.submit(kind).await
             }
         }
     }
 
+    /// Lists all unfinished jobs.
+    pub fn list_jobs(&self) -> Vec<airup_sdk::system::Job> {
+        self.supervisors.jobs.list()
+    }
+
+    /// Waits for the specific job to finish.
+    ///
+    /// # Errors
+    /// This method would fail if the job was not found or it has failed.
+    pub async fn wait_job(&self, id: u64) -> Result<(), Error> {
+        self.supervisors.jobs.wait(id).await
+    }
+
+    /// Cancels the specific job. A queued job is removed from the queue, and a running job is interrupted.
+    ///
+    /// # Errors
+    /// This method would fail if the job was not found, or it is running an uninterruptible task.
+    pub async fn cancel_job(&self, id: u64) -> Result<(), Error> {
+        let job = self.supervisors.jobs.get(id).ok_or(Error::NotFound)?;
+        let supervisor = self
+            .supervisors
+            .get(job.service())
+            .await
+            .ok_or(Error::NotFound)?;
+        supervisor.cancel_job(id).await
+    }
+
+    /// Starts the specific service, returns a handle of the `StartService` job on success.
+    ///
+    /// # Errors
+    /// This method would fail if the service is already active or the specific service was not found.
+    pub async fn start_service(&self, name: &str) -> Result<Arc<dyn TaskHandle>, Error> {
+        Ok(self.submit_job(name, JobKind::Start).await?)
+    }
+
     /// Queries the specific service, returns queried information about the service.
     ///
     /// # Errors
@@ -819,14 +1428,72 @@ impl crate::app::Airupd {
         }
     }
 
-    /// Stops the specific service, returns a handle of the spawned `StopService` task on success.
+    /// Stops the specific service, returns a handle of the `StopService` job on success.
     ///
     /// # Errors
-    /// This method would fail if the service is not active, having another running task or the specific service was not
-    /// found.
+    /// This method would fail if the service is not active or the specific service was not found.
     pub async fn stop_service(&self, name: &str) -> Result<Arc<dyn TaskHandle>, Error> {
+        Ok(self.submit_job(name, JobKind::Stop).await?)
+    }
+
+    /// Spawns a command in the execution environment of the specific service, with the given standard I/O file descriptors.
+    ///
+    /// The command runs as the same user, with the same environment variables, working directory, root directory and realm as
+    /// the service. If the service is not cached, it runs outside of any realm, and the service is not cached by this.
+    ///
+    /// # Errors
+    /// This method would fail if the specific service was not found, the service runs as a dynamic user while it has none
+    /// allocated, or the command cannot be spawned.
+    pub async fn exec_in_service(
+        &self,
+        name: &str,
+        command: ExecCommand,
+        stdio: [OwnedFd; 3],
+    ) -> Result<crate::ace::Child, Error> {
         match self.supervisors.get(name).await {
-            Some(supervisor) => Ok(supervisor.stop().await?),
+            Some(supervisor) => supervisor.exec(command, stdio).await,
+            None => exec_in(&*self.detached_context(name).await?, command, stdio).await,
+        }
+    }
+
+    /// Expands an ACE script in the execution environment of the specific service, without running it. Returns argument
+    /// vectors of all commands in the script, in order they appear.
+    ///
+    /// # Errors
+    /// This method would fail if the specific service was not found, the service runs as a dynamic user while it has none
+    /// allocated, or the script cannot be parsed or expanded.
+    pub async fn expand_ace_in_service(
+        &self,
+        name: &str,
+        script: String,
+    ) -> Result<Vec<Vec<String>>, Error> {
+        match self.supervisors.get(name).await {
+            Some(supervisor) => supervisor.expand_ace(script).await,
+            None => expand_ace_in(&*self.detached_context(name).await?, &script).await,
+        }
+    }
+
+    /// Returns a detached context of the specific service, which is not cached.
+    ///
+    /// The service is not supervised by this, so it is not cached as a side effect. Since a service which is not cached has no
+    /// process, commands spawned in this context run outside of any realm: a realm created for them would outlive its owner
+    /// and be picked up by the service once it gets supervised.
+    async fn detached_context(&self, name: &str) -> Result<Arc<SupervisorContext>, Error> {
+        Ok(SupervisorContext::detached(
+            self.storage.get_service_patched(name).await?,
+        ))
+    }
+
+    /// Forces the specific service to stop, and waits until its processes are killed.
+    ///
+    /// # Errors
+    /// This method would fail if the service does not have a process.
+    pub async fn kill_service(&self, name: &str) -> Result<(), Error> {
+        match self.supervisors.get(name).await {
+            Some(supervisor) => {
+                supervisor.kill().await?.wait().await?;
+                Ok(())
+            }
             None => {
                 self.storage.get_service_patched(name).await?;
                 Err(Error::NotStarted)
@@ -834,28 +1501,27 @@ impl crate::app::Airupd {
         }
     }
 
-    /// Forces the specific service to stop.
+    /// Lists processes belonging to the specific service.
     ///
     /// # Errors
-    /// This method would fail if the service does not have a process.
-    pub async fn kill_service(&self, name: &str) -> Result<(), Error> {
+    /// This method would fail if the specific service was not found.
+    pub async fn list_service_processes(&self, name: &str) -> Result<Vec<ServiceProcess>, Error> {
         match self.supervisors.get(name).await {
-            Some(supervisor) => Ok(supervisor.kill().await?),
+            Some(supervisor) => Ok(supervisor.list_processes().await),
             None => {
                 self.storage.get_service_patched(name).await?;
-                Err(Error::NotStarted)
+                Ok(Vec::new())
             }
         }
     }
 
-    /// Reloads the specific service, returns a handle of the spawned `ReloadService` task on success.
+    /// Sends a signal to the main process of the specific service, or all of its processes if `all` is `true`.
     ///
     /// # Errors
-    /// This method would fail if the service is not active, having another running task or the specific service was not
-    /// found.
-    pub async fn reload_service(&self, name: &str) -> Result<Arc<dyn TaskHandle>, Error> {
+    /// This method would fail if the service does not have a process.
+    pub async fn signal_service(&self, name: &str, signum: i32, all: bool) -> Result<(), Error> {
         match self.supervisors.get(name).await {
-            Some(supervisor) => Ok(supervisor.reload().await?),
+            Some(supervisor) => supervisor.signal(signum, all).await,
             None => {
                 self.storage.get_service_patched(name).await?;
                 Err(Error::NotStarted)
@@ -863,6 +1529,27 @@ impl crate::app::Airupd {
         }
     }
 
+    /// Restarts the specific service in the given mode, returns a handle of the `RestartService` job on success.
+    ///
+    /// # Errors
+    /// This method would fail if the specific service was not found. If the mode is [`RestartMode::Restart`], the job fails if
+    /// the service is not active.
+    pub async fn restart_service(
+        &self,
+        name: &str,
+        mode: RestartMode,
+    ) -> Result<Arc<dyn TaskHandle>, Error> {
+        Ok(self.submit_job(name, JobKind::Restart(mode)).await?)
+    }
+
+    /// Reloads the specific service, returns a handle of the `ReloadService` job on success.
+    ///
+    /// # Errors
+    /// This method would fail if the service is not active or the specific service was not found.
+    pub async fn reload_service(&self, name: &str) -> Result<Arc<dyn TaskHandle>, Error> {
+        Ok(self.submit_job(name, JobKind::Reload).await?)
+    }
+
     /// Caches the specific service.
     ///
     /// # Errors
@@ -910,13 +1597,30 @@ impl crate::app::Airupd {
     }
 }
 
+/// A command to execute in the execution environment of a service.
+#[derive(Debug, Clone)]
+pub enum ExecCommand {
+    /// A program followed by its arguments, which is spawned directly.
+    Argv(Vec<String>),
+
+    /// An ACE script.
+    Ace(String),
+}
+
 /// Representation of a request sent to a supervisor.
 enum Request {
     Query(oneshot::Sender<QueryService>),
-    Start(oneshot::Sender<Result<Arc<dyn TaskHandle>, Error>>),
-    Stop(oneshot::Sender<Result<Arc<dyn TaskHandle>, Error>>),
-    Kill(oneshot::Sender<Result<(), Error>>),
-    Reload(oneshot::Sender<Result<Arc<dyn TaskHandle>, Error>>),
+    Submit(JobKind, oneshot::Sender<Result<Arc<Job>, Error>>),
+    CancelJob(u64, oneshot::Sender<Result<(), Error>>),
+    Exec(
+        ExecCommand,
+        [OwnedFd; 3],
+        oneshot::Sender<Result<crate::ace::Child, Error>>,
+    ),
+    ExpandAce(String, oneshot::Sender<Result<Vec<Vec<String>>, Error>>),
+    Kill(oneshot::Sender<Result<Arc<dyn TaskHandle>, Error>>),
+    ListProcesses(oneshot::Sender<Vec<ServiceProcess>>),
+    Signal(i32, bool, oneshot::Sender<Result<(), Error>>),
     UpdateManifest(Box<Service>, oneshot::Sender<Result<Service, Error>>),
     InterruptTask(oneshot::Sender<Result<Arc<dyn TaskHandle>, Error>>),
     Autostart(
@@ -944,7 +1648,100 @@ async fn wait(lock: &mut Option<Child>) -> Option<Wait> {
     wait
 }
 
+/// Returns information about processes of a service with the given realm and main PID.
+///
+/// If the realm can list its processes, processes in the realm are returned. Otherwise, processes in the main process' tree are
+/// returned.
+fn processes(realm: Option<&Realm>, pid: Option<i64>) -> Vec<ProcessInfo> {
+    if let Some(pids) = realm.and_then(|x| x.pids().ok()) {
+        return pids
+            .into_iter()
+            .filter_map(|x| airupfx::process::process(x).ok())
+            .collect();
+    }
+
+    match pid {
+        Some(pid) => airupfx::process::tree(pid).unwrap_or_default(),
+        None => Vec::new(),
+    }
+}
+
+/// Gathers resource usage statistics of a service from its processes, preferring statistics reported by the realm.
+///
+/// Statistics reported by the realm also include processes that have exited, so they are more accurate than summing the
+/// statistics of running processes up.
+fn resource_usage(realm: Option<&Realm>, processes: &[ProcessInfo]) -> Option<ResourceUsage> {
+    let mut usage = ResourceUsage::default();
+    let mut memory_peak = 0;
+    for info in processes {
+        usage.cpu_user += info.cpu_user;
+        usage.cpu_system += info.cpu_system;
+        usage.io_read += info.io_read;
+        usage.io_write += info.io_write;
+        usage.tasks += info.threads;
+        memory_peak += info.memory_peak;
+    }
+    if !processes.is_empty() {
+        usage.memory_peak = Some(memory_peak);
+    }
+
+    if let Some(realm) = realm {
+        if let Ok(cpu) = realm.cpu_usage() {
+            usage.cpu_user = cpu.user;
+            usage.cpu_system = cpu.system;
+        }
+        if let Ok(peak) = realm.memory_peak() {
+            usage.memory_peak = Some(peak as u64);
+        }
+    }
+
+    (usage != ResourceUsage::default()).then_some(usage)
+}
+
 /// Set up a realm.
+/// Spawns a command in the execution environment described by the given [`SupervisorContext`], with the given standard I/O
+/// file descriptors.
+async fn exec_in(
+    context: &SupervisorContext,
+    command: ExecCommand,
+    stdio: [OwnedFd; 3],
+) -> Result<crate::ace::Child, Error> {
+    let [stdin, stdout, stderr] = stdio.map(|x| airupfx::process::Stdio::Fd(Arc::new(x)));
+    let mut ace = exec_ace(context).await?;
+    ace.env.stdin(stdin).stdout(stdout).stderr(stderr);
+
+    match command {
+        ExecCommand::Argv(argv) => {
+            let (program, args) = argv
+                .split_first()
+                .ok_or_else(|| Error::invalid_params("command must not be empty"))?;
+            Ok(ace.spawn(program, args).await?)
+        }
+        ExecCommand::Ace(script) => Ok(ace.run(&script).await?),
+    }
+}
+
+/// Expands an ACE script in the execution environment described by the given [`SupervisorContext`], without running it.
+async fn expand_ace_in(
+    context: &SupervisorContext,
+    script: &str,
+) -> Result<Vec<Vec<String>>, Error> {
+    Ok(exec_ace(context).await?.expand(script)?)
+}
+
+/// Creates an [`crate::ace::Ace`] instance for executing user-requested commands in the given [`SupervisorContext`].
+///
+/// A service running as a dynamic user has no user to run the commands as until it is started, so this fails rather than
+/// running them as the user of `airupd`.
+async fn exec_ace(context: &SupervisorContext) -> Result<crate::ace::Ace, Error> {
+    if context.service.env.dynamic_user && context.dynamic_user().is_none() {
+        return Err(Error::unsupported(
+            "the service runs as a dynamic user, which is not allocated until it is started",
+        ));
+    }
+    task::ace(context).await
+}
+
 fn setup_realm(realm: &Option<Arc<Realm>>, service: &Service) {
     if let Some(realm) = &realm {
         if let Some(x) = service.reslimit.cpu {
@@ -956,3 +1753,60 @@ fn setup_realm(realm: &Option<Arc<Realm>>, service: &Service) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::KillTarget;
+    use airup_sdk::files::service::KillMode;
+    use std::{os::unix::process::CommandExt, time::Duration};
+
+    #[tokio::test]
+    async fn kill_target() {
+        let sleep = |pgid| {
+            std::process::Command::new("sleep")
+                .arg("30")
+                .process_group(pgid)
+                .spawn()
+                .unwrap()
+        };
+        // All processes are children of the test, so that they are reaped by it rather than left as zombies
+        let mut leader = sleep(0);
+        let leader_pid = leader.id() as i64;
+        let mut grouped = sleep(leader_pid as _);
+        let mut member = std::process::Command::new("sleep")
+            .arg("30")
+            .spawn()
+            .unwrap();
+        let member_pid = member.id() as i64;
+
+        // A process group is only signaled if the main process leads it
+        let select = |pid, mode| KillTarget::select(None, pid, mode);
+        assert!(matches!(
+            select(leader_pid, KillMode::Main),
+            KillTarget::Main
+        ));
+        assert!(
+            matches!(select(leader_pid, KillMode::Group), KillTarget::Group(x) if x == leader_pid)
+        );
+        assert!(matches!(
+            select(member_pid, KillMode::Group),
+            KillTarget::Main
+        ));
+        assert!(
+            matches!(select(leader_pid, KillMode::Realm), KillTarget::Processes(x) if x.contains(&leader_pid))
+        );
+
+        // Signaling the group reaches all processes in it
+        let target = select(leader_pid, KillMode::Group);
+        assert!(target.is_alive());
+        let dummy = crate::ace::Child::Builtin(tokio::spawn(async { 0 }).into());
+        target.send(&dummy, libc::SIGKILL).await.unwrap();
+        // The processes may have been reaped by the child queue, which is started by other tests
+        _ = leader.wait();
+        _ = grouped.wait();
+        assert!(target.wait_timeout(Some(Duration::from_secs(5))).await);
+
+        member.kill().unwrap();
+        _ = member.wait();
+    }
+}
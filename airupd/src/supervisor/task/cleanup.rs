@@ -95,6 +95,7 @@ impl CleanupService {
             &airupfx::time::countdown(self.context.service.exec.stop_timeout()),
         )
         .await;
+        self.context.release_dynamic_user();
 
         self.important.store(false, atomic::Ordering::Release);
         self.helper
@@ -105,6 +106,7 @@ impl CleanupService {
 
         if self.retry {
             self.important.store(true, atomic::Ordering::Release);
+            self.context.restarts.mark();
             let handle = super::start::start(self.context.clone());
             tokio::select! {
                 _ = handle.wait() => {},
@@ -130,6 +132,8 @@ pub async fn cleanup_service(
     if let Some(x) = &service.service.pid_file {
         _ = tokio::fs::remove_file(x).await;
     }
+    super::remove_credentials(service).await;
+    super::remove_runtime_directories(service).await;
 
     if let Some(x) = &service.exec.post_stop {
         for line in x.lines() {
@@ -3,14 +3,27 @@
 pub mod cleanup;
 pub mod health_check;
 pub mod reload;
+pub mod restart;
 pub mod start;
 pub mod stop;
 
 use super::SupervisorContext;
 use crate::{ace::Ace, supervisor::logging::LogCallback};
-use airup_sdk::Error;
+use airup_sdk::{Error, files::service::DirectoryKind};
 use airupfx::prelude::*;
-use std::{future::Future, path::PathBuf};
+use std::{
+    ffi::{CStr, CString},
+    fs::File,
+    future::Future,
+    os::{
+        fd::{AsRawFd, FromRawFd, RawFd},
+        unix::{
+            ffi::OsStrExt,
+            fs::{MetadataExt, PermissionsExt},
+        },
+    },
+    path::{Path, PathBuf},
+};
 use tokio::sync::watch;
 
 /// Representation of handle to a task.
@@ -167,6 +180,20 @@ async fn ace_environment(
         airup_sdk::files::service::Stdio::Log => log(y),
     };
 
+    let mut env_file_vars = Vec::new();
+    for (path, optional) in env.env_files() {
+        match airupfx::env::dotenv::read(path).await {
+            Ok(x) => env_file_vars.extend(x),
+            Err(err) if optional && err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => {
+                return Err(anyhow::anyhow!(
+                    "failed to load environment file `{}`: {err}",
+                    path.display()
+                ));
+            }
+        }
+    }
+
     let vars = env
         .vars
         .iter()
@@ -192,11 +219,26 @@ async fn ace_environment(
         .stdout(to_ace(env.stdout.clone(), 1))
         .stderr(to_ace(env.stderr.clone(), 2))
         .clear_vars(env.clear_vars)
+        .vars::<String, _, String>(env_file_vars.into_iter().map(|(k, v)| (k, Some(v))))
         .vars::<String, _, String>(vars)
         .root_dir::<PathBuf, _>(env.root_dir.clone())
         .working_dir::<PathBuf, _>(env.working_dir.clone())
         .setsid(true);
 
+    if !env.groups.is_empty() {
+        let mut groups = result.groups.clone().unwrap_or_default();
+        for group in &env.groups {
+            groups.push(match group {
+                airup_sdk::files::service::Group::Id(x) => *x,
+                airup_sdk::files::service::Group::Name(name) => {
+                    airupfx::env::with_group_by_name(name, |x| **x.id())
+                        .ok_or_else(|| anyhow::anyhow!("group `{name}` does not exist"))?
+                }
+            });
+        }
+        result.groups(groups);
+    }
+
     Ok(result)
 }
 
@@ -205,15 +247,317 @@ pub(in crate::supervisor) async fn ace(context: &SupervisorContext) -> Result<Ac
     let mut ace = Ace::new();
 
     ace.realm.clone_from(&context.realm);
+    ace.stop_signal = context.service.exec.stop_signal();
+    ace.kill_signal = context.service.exec.kill_signal();
+    ace.grace_period = context.service.exec.stop_grace_period();
     ace.env = ace_environment(&context.service)
         .await
         .map_err(|x| Error::Io {
             message: x.to_string(),
         })?;
     ace.env.var("AIRUP_SERVICE", context.service.name.clone());
+    if !context.service.env.credentials.is_empty() {
+        ace.env.var(
+            "AIRUP_CREDENTIALS_DIRECTORY",
+            credentials_dir(&context.service),
+        );
+    }
+    if let Some(id) = context.dynamic_user() {
+        let groups = ace.env.groups.take().unwrap_or_default();
+        ace.env.uid(id).gid(id).groups(groups);
+    }
+    for kind in DirectoryKind::ALL {
+        let mut paths: Vec<_> = context.service.directories.paths(kind).collect();
+        if kind == DirectoryKind::State && context.dynamic_user().is_some() {
+            paths.insert(0, dynamic_user_state_dir(&context.service));
+        }
+        if !paths.is_empty() {
+            // Paths containing `:` are rejected when validating the manifest, so joining never fails here.
+            ace.env.var(
+                kind.env_var(),
+                std::env::join_paths(paths).unwrap_or_default(),
+            );
+        }
+    }
     if let Some(pid) = context.pid().await {
         ace.env.var("AIRUP_SERVICE_MAIN_PID", pid.to_string());
     }
 
     Ok(ace)
 }
+
+/// Returns path of the private state directory of the given service, which runs as a dynamic user.
+fn dynamic_user_state_dir(service: &airup_sdk::files::Service) -> PathBuf {
+    crate::app::airupd()
+        .storage
+        .config
+        .system_conf
+        .system
+        .dynamic_user_state_dir
+        .join(&service.name)
+}
+
+/// Allocates a dynamic user for the service if it is configured to run as one, and prepares its private state directory.
+pub(in crate::supervisor) async fn setup_dynamic_user(
+    context: &SupervisorContext,
+) -> Result<(), Error> {
+    if !context.service.env.dynamic_user {
+        return Ok(());
+    }
+
+    let id = context.allocate_dynamic_user()?;
+    let dir = dynamic_user_state_dir(&context.service);
+    let io_error = |err: std::io::Error| Error::Io {
+        message: err.to_string(),
+    };
+    if let Some(parent) = dir.parent() {
+        tokio::fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o711)
+            .create(parent)
+            .await
+            .map_err(io_error)?;
+    }
+    tokio::fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(&dir)
+        .await
+        .map_err(io_error)?;
+
+    // The allocated ID may differ from the last run, in which case ownership of the whole directory is updated.
+    let metadata = tokio::fs::metadata(&dir).await.map_err(io_error)?;
+    if metadata.uid() == id && metadata.gid() == id {
+        return Ok(());
+    }
+    tokio::task::spawn_blocking(move || chown_recursive(&dir, Some(id), Some(id)))
+        .await
+        .map_err(|err| Error::internal(err.to_string()))?
+        .map_err(io_error)
+}
+
+/// Changes ownership of the given directory and everything inside it.
+///
+/// Since the directory is writable by the service, it is walked by file descriptors, so that the daemon is never tricked into
+/// changing ownership of files outside it: symbolic links are never followed, other filesystems are never entered, and files
+/// with multiple hard links are skipped. Only directories and regular files are changed.
+fn chown_recursive(path: &Path, uid: Option<u32>, gid: Option<u32>) -> std::io::Result<()> {
+    let path = CString::new(path.as_os_str().as_bytes())?;
+    let dir = open_at(libc::AT_FDCWD, &path)?;
+    let dev = dir.metadata()?.dev();
+    chown_tree(dir, dev, uid, gid)
+}
+
+fn chown_tree(file: File, dev: u64, uid: Option<u32>, gid: Option<u32>) -> std::io::Result<()> {
+    let metadata = file.metadata()?;
+    let eligible = metadata.is_dir() || (metadata.is_file() && metadata.nlink() == 1);
+    if metadata.dev() != dev || !eligible {
+        return Ok(());
+    }
+    std::os::unix::fs::fchown(&file, uid, gid)?;
+    if !metadata.is_dir() {
+        return Ok(());
+    }
+
+    for name in read_dir_at(&file)? {
+        // Only directories and regular files are opened, since opening other kinds of files may have side effects
+        let mut stat = unsafe { std::mem::zeroed::<libc::stat>() };
+        let result = unsafe {
+            libc::fstatat(
+                file.as_raw_fd(),
+                name.as_ptr(),
+                &mut stat,
+                libc::AT_SYMLINK_NOFOLLOW,
+            )
+        };
+        let kind = stat.st_mode & libc::S_IFMT;
+        if result != 0 || (kind != libc::S_IFDIR && kind != libc::S_IFREG) {
+            continue;
+        }
+        match open_at(file.as_raw_fd(), &name) {
+            Ok(child) => chown_tree(child, dev, uid, gid)?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) if err.raw_os_error() == Some(libc::ELOOP) => {}
+            Err(err) => return Err(err),
+        }
+    }
+
+    Ok(())
+}
+
+/// Opens a file relative to the directory, without following symbolic links.
+fn open_at(dir: RawFd, name: &CStr) -> std::io::Result<File> {
+    let flags =
+        libc::O_RDONLY | libc::O_NOFOLLOW | libc::O_NONBLOCK | libc::O_NOCTTY | libc::O_CLOEXEC;
+    match unsafe { libc::openat(dir, name.as_ptr(), flags) } {
+        -1 => Err(std::io::Error::last_os_error()),
+        fd => Ok(unsafe { File::from_raw_fd(fd) }),
+    }
+}
+
+/// Returns names of entries in the opened directory, except `.` and `..`.
+fn read_dir_at(dir: &File) -> std::io::Result<Vec<CString>> {
+    let fd = unsafe { libc::fcntl(dir.as_raw_fd(), libc::F_DUPFD_CLOEXEC, 0) };
+    if fd == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    let stream = unsafe { libc::fdopendir(fd) };
+    if stream.is_null() {
+        let err = std::io::Error::last_os_error();
+        unsafe { libc::close(fd) };
+        return Err(err);
+    }
+
+    let mut names = Vec::new();
+    loop {
+        let entry = unsafe { libc::readdir(stream) };
+        if entry.is_null() {
+            break;
+        }
+        let name = unsafe { CStr::from_ptr((*entry).d_name.as_ptr()) };
+        if name != c"." && name != c".." {
+            names.push(name.to_owned());
+        }
+    }
+    unsafe { libc::closedir(stream) };
+
+    Ok(names)
+}
+
+/// Returns path of the directory that credentials of the given service are stored in.
+fn credentials_dir(service: &airup_sdk::files::Service) -> PathBuf {
+    airup_sdk::build::manifest()
+        .runtime_dir
+        .join("credentials")
+        .join(&service.name)
+}
+
+/// Copies credentials of the given service into its credentials directory, which is only accessible by the service's user.
+///
+/// The directory is recreated on every call, so credentials are always up to date when the service starts.
+pub(in crate::supervisor) async fn setup_credentials(
+    service: &airup_sdk::files::Service,
+    env: &airupfx::process::CommandEnv,
+) -> std::io::Result<()> {
+    if service.env.credentials.is_empty() {
+        return Ok(());
+    }
+
+    let dir = credentials_dir(service);
+    _ = tokio::fs::remove_dir_all(&dir).await;
+    if let Some(parent) = dir.parent() {
+        tokio::fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o711)
+            .create(parent)
+            .await?;
+    }
+    tokio::fs::DirBuilder::new()
+        .mode(0o700)
+        .create(&dir)
+        .await?;
+
+    for (name, source) in &service.env.credentials {
+        let content = tokio::fs::read(source).await.map_err(|err| {
+            std::io::Error::new(
+                err.kind(),
+                format!("failed to read credential `{name}`: {err}"),
+            )
+        })?;
+        let path = dir.join(name);
+        let mut file = tokio::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o400)
+            .open(&path)
+            .await?;
+        tokio::io::AsyncWriteExt::write_all(&mut file, &content).await?;
+        std::os::unix::fs::chown(&path, env.uid, env.gid)?;
+    }
+    std::os::unix::fs::chown(&dir, env.uid, env.gid)?;
+
+    Ok(())
+}
+
+/// Creates managed directories of the given service, owned by the service's user.
+///
+/// If a directory already exists but is owned by another user, e.g. the service's user has changed, ownership of everything
+/// inside it is updated.
+pub(in crate::supervisor) async fn setup_directories(
+    service: &airup_sdk::files::Service,
+    env: &airupfx::process::CommandEnv,
+) -> std::io::Result<()> {
+    let directories = &service.directories;
+    for kind in DirectoryKind::ALL {
+        for path in directories.paths(kind) {
+            if let Some(parent) = path.parent() {
+                tokio::fs::DirBuilder::new()
+                    .recursive(true)
+                    .create(parent)
+                    .await?;
+            }
+            tokio::fs::DirBuilder::new()
+                .recursive(true)
+                .mode(directories.mode)
+                .create(&path)
+                .await?;
+            tokio::fs::set_permissions(&path, std::fs::Permissions::from_mode(directories.mode))
+                .await?;
+
+            let metadata = tokio::fs::metadata(&path).await?;
+            let owner_changed = env.uid.is_some_and(|x| x != metadata.uid())
+                || env.gid.is_some_and(|x| x != metadata.gid());
+            if owner_changed {
+                let (uid, gid) = (env.uid, env.gid);
+                tokio::task::spawn_blocking(move || chown_recursive(&path, uid, gid))
+                    .await
+                    .map_err(std::io::Error::other)??;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Removes managed runtime directories of the given service.
+pub(in crate::supervisor) async fn remove_runtime_directories(service: &airup_sdk::files::Service) {
+    for path in service.directories.paths(DirectoryKind::Runtime) {
+        _ = tokio::fs::remove_dir_all(path).await;
+    }
+}
+
+/// Removes the credentials directory of the given service.
+pub(in crate::supervisor) async fn remove_credentials(service: &airup_sdk::files::Service) {
+    if !service.env.credentials.is_empty() {
+        _ = tokio::fs::remove_dir_all(credentials_dir(service)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::MetadataExt;
+
+    #[test]
+    fn chown_recursive() {
+        let dir = std::env::temp_dir().join(format!("airup-test-chown-{}", std::process::id()));
+        let outside =
+            std::env::temp_dir().join(format!("airup-test-chown-{}.outside", std::process::id()));
+        std::fs::create_dir_all(dir.join("a")).unwrap();
+        std::fs::write(dir.join("a/file"), "").unwrap();
+        std::fs::write(&outside, "").unwrap();
+        std::fs::hard_link(&outside, dir.join("link")).unwrap();
+        std::os::unix::fs::symlink(&outside, dir.join("symlink")).unwrap();
+        let (uid, gid) = (unsafe { libc::geteuid() }, unsafe { libc::getegid() });
+
+        // Changing to the current owner always succeeds, and files outside the directory are never touched
+        super::chown_recursive(&dir, Some(uid), Some(gid)).unwrap();
+        if uid == 0 {
+            super::chown_recursive(&dir, Some(65534), Some(65534)).unwrap();
+            assert_eq!(std::fs::metadata(dir.join("a/file")).unwrap().uid(), 65534);
+            assert_eq!(std::fs::metadata(&outside).unwrap().uid(), 0);
+        }
+
+        std::fs::remove_dir_all(&dir).unwrap();
+        std::fs::remove_file(&outside).unwrap();
+    }
+}
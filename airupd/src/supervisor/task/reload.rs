@@ -1,8 +1,13 @@
 use super::*;
-use crate::ace::CommandExitError;
-use airup_sdk::prelude::*;
+use crate::{ace::CommandExitError, app::airupd};
+use airup_sdk::{files::service::Kind, prelude::*};
 use airupfx::prelude::*;
 use std::{sync::Arc, time::Duration};
+use tokio::task::JoinHandle;
+
+/// Time to wait for a `notify`-kind service to trigger `notify_reloaded` if `reload-timeout` is not set. If the event is not
+/// triggered in time, the reload times out.
+const DEFAULT_NOTIFY_RELOADED_TIMEOUT: Duration = Duration::from_secs(10);
 
 #[derive(Debug)]
 pub struct ReloadServiceHandle {
@@ -26,15 +31,15 @@ impl TaskHandle for ReloadServiceHandle {
     }
 }
 
-pub(in crate::supervisor) async fn start(context: &SupervisorContext) -> Arc<dyn TaskHandle> {
+pub(in crate::supervisor) async fn start(context: Arc<SupervisorContext>) -> Arc<dyn TaskHandle> {
     let (handle, helper) = task_helper();
 
     let reload_service = ReloadService {
         helper,
-        ace: super::ace(context).await,
+        ace: super::ace(&context).await,
         status: context.status.get(),
-        reload_cmd: context.service.exec.reload.clone(),
         reload_timeout: context.service.exec.reload_timeout(),
+        context,
     };
     reload_service.start();
 
@@ -45,8 +50,8 @@ struct ReloadService {
     helper: TaskHelper,
     ace: Result<Ace, Error>,
     status: Status,
-    reload_cmd: Option<String>,
     reload_timeout: Option<Duration>,
+    context: Arc<SupervisorContext>,
 }
 impl ReloadService {
     fn start(mut self) {
@@ -65,10 +70,33 @@ impl ReloadService {
 
         self.helper
             .would_interrupt(async {
-                if let Some(reload_cmd) = &self.reload_cmd {
-                    ace.run_wait_timeout(reload_cmd, self.reload_timeout)
-                        .await??;
+                // Watch before triggering the reload, so that an early `notify_reloaded` event is not missed.
+                let watcher = (self.context.service.service.kind == Kind::Notify)
+                    .then(|| ReloadedWatcher::spawn(&self.context.service.name));
+                let exec = &self.context.service.exec;
+                let countdown = airupfx::time::countdown(self.reload_timeout);
+
+                if let Some(reload_cmd) = &exec.reload {
+                    if let Err(err) = ace.run_wait_timeout(reload_cmd, countdown.left()).await? {
+                        return Ok(Err(err));
+                    }
+                } else if let Some(signum) = exec.reload_signal() {
+                    match &*self.context.child.read().await {
+                        Some(child) => child.send_signal(signum).await?,
+                        None => {
+                            return Err(Error::unsupported(
+                                "cannot signal a service without a process",
+                            ));
+                        }
+                    }
+                } else {
+                    return Ok(Ok(()));
+                }
+
+                if let Some(watcher) = watcher {
+                    watcher.wait(&countdown).await?;
                 }
+
                 Ok::<_, Error>(Ok::<_, CommandExitError>(()))
             })
             .await???;
@@ -76,3 +104,43 @@ impl ReloadService {
         Ok(())
     }
 }
+
+/// Watches the event bus for `notify_reloaded` of a service in background, which keeps draining events, so that the bus is
+/// never blocked by a slow reload. The watcher stops when dropped.
+struct ReloadedWatcher(JoinHandle<bool>);
+impl ReloadedWatcher {
+    fn spawn(name: &str) -> Self {
+        let mut events = airupd().events.subscribe();
+        let interests = [name.to_owned(), format!("{name}.airs")];
+        Self(tokio::spawn(async move {
+            loop {
+                match events.recv().await {
+                    Ok(event)
+                        if event.id == "notify_reloaded" && interests.contains(&event.payload) =>
+                    {
+                        break true;
+                    }
+                    Ok(_) | Err(async_broadcast::RecvError::Overflowed(_)) => {}
+                    Err(async_broadcast::RecvError::Closed) => break false,
+                }
+            }
+        }))
+    }
+
+    /// Waits until the service reports that it has finished reloading, by triggering the `notify_reloaded` event.
+    async fn wait(mut self, countdown: &Countdown) -> Result<(), Error> {
+        let timeout = countdown.left().unwrap_or(DEFAULT_NOTIFY_RELOADED_TIMEOUT);
+        let reloaded = tokio::time::timeout(timeout, &mut self.0)
+            .await
+            .map_err(|_| Error::TimedOut)?;
+        match reloaded {
+            Ok(true) => Ok(()),
+            _ => Err(Error::internal("the event bus was closed")),
+        }
+    }
+}
+impl Drop for ReloadedWatcher {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
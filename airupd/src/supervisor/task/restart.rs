@@ -0,0 +1,96 @@
+//! # The `RestartService` Task
+//! This restarts a service atomically in the supervisor, so that no other tasks can be started between stopping and starting
+//! the service.
+
+use super::*;
+use airup_sdk::system::{RestartMode, Status};
+use airupfx::prelude::*;
+use std::sync::Arc;
+
+#[derive(Debug)]
+pub struct RestartServiceHandle {
+    helper: TaskHelperHandle,
+}
+impl TaskHandle for RestartServiceHandle {
+    fn task_class(&self) -> &'static str {
+        "RestartService"
+    }
+
+    fn is_important(&self) -> bool {
+        true
+    }
+
+    fn send_interrupt(&self) {
+        self.helper.send_interrupt()
+    }
+
+    fn wait(&self) -> BoxFuture<'_, Result<TaskFeedback, Error>> {
+        self.helper.wait()
+    }
+}
+
+pub(in crate::supervisor) fn start(
+    context: Arc<SupervisorContext>,
+    mode: RestartMode,
+) -> Arc<dyn TaskHandle> {
+    let (handle, helper) = task_helper();
+
+    let restart_service = RestartService {
+        helper,
+        context,
+        mode,
+    };
+    restart_service.start();
+
+    Arc::new(RestartServiceHandle { helper: handle })
+}
+
+#[derive(Debug)]
+struct RestartService {
+    helper: TaskHelper,
+    context: Arc<SupervisorContext>,
+    mode: RestartMode,
+}
+impl RestartService {
+    fn start(mut self) {
+        tokio::spawn(async move {
+            let val = self.run().await;
+            self.helper.finish(val);
+        });
+    }
+
+    async fn run(&mut self) -> Result<(), Error> {
+        let active = self.context.status.get() == Status::Active;
+        let exec = &self.context.service.exec;
+        let reloadable = exec.reload.is_some() || exec.reload_signal.is_some();
+
+        match self.mode {
+            RestartMode::Restart if !active => return Err(Error::NotStarted),
+            RestartMode::ReloadOrRestart if active && reloadable => {
+                let handle = super::reload::start(self.context.clone()).await;
+                return self.run_subtask(handle).await;
+            }
+            _ => {}
+        }
+
+        if active {
+            let handle = super::stop::start(self.context.clone());
+            self.run_subtask(handle).await?;
+            self.context.restarts.mark();
+        }
+
+        let handle = super::start::start(self.context.clone());
+        self.run_subtask(handle).await
+    }
+
+    /// Waits for a subtask to complete. If this task is interrupted, the subtask is interrupted too.
+    async fn run_subtask(&self, handle: Arc<dyn TaskHandle>) -> Result<(), Error> {
+        match self.helper.would_interrupt(handle.wait()).await {
+            Ok(result) => result.map(|_| ()),
+            Err(err) => {
+                handle.send_interrupt();
+                Err(err)
+            }
+        }
+    }
+}
@@ -1,8 +1,8 @@
 use super::*;
-use crate::app::airupd;
+use crate::{app::airupd, supervisor::conditions};
 use airup_sdk::{
     files::service::Kind,
-    system::{ServiceStartReason, Status},
+    system::{ServiceStartReason, StartTimings, Status},
 };
 use airupfx::prelude::*;
 use std::sync::Arc;
@@ -47,6 +47,16 @@ impl StartService {
     fn start(mut self) {
         tokio::spawn(async move {
             let val = self.run().await;
+            if let Err(err) = &val {
+                self.context.release_dynamic_user();
+
+                // Credentials and runtime directories are normally removed on exit of the service, which never happens
+                // if the service failed to start
+                if !matches!(err, Error::Started) {
+                    super::remove_credentials(&self.context.service).await;
+                    super::remove_runtime_directories(&self.context.service).await;
+                }
+            }
             self.helper.finish(val);
         });
     }
@@ -66,21 +76,57 @@ impl StartService {
             ));
         }
 
+        *self.context.start_timings.lock().unwrap() = Some(StartTimings {
+            requested: airupfx::time::timestamp_ms(),
+            ..Default::default()
+        });
+
         // Auto saving of last error is enabled for this task
         self.context.last_error.set(None);
         self.context.last_error.set_autosave(true);
 
+        // The task is skipped if any of the conditions is not met, and fails if any of the assertions is not met
+        let skipped = conditions::check(&self.context.service.conditions)
+            .await
+            .err();
+        let skip = skipped.is_some();
+        *self.context.skipped.lock().unwrap() = skipped;
+        if skip {
+            return Ok(());
+        }
+        conditions::check(&self.context.service.assertions)
+            .await
+            .map_err(|message| Error::AssertionFailed { message })?;
+
+        super::setup_dynamic_user(&self.context).await?;
         let ace = super::ace(&self.context).await?;
 
         self.helper.would_interrupt(self.solve_deps()).await??;
+        self.context
+            .record_timing(|timings, now| timings.dependencies_satisfied = Some(now));
+
+        super::setup_credentials(&self.context.service, &ace.env)
+            .await
+            .map_err(|err| Error::Io {
+                message: err.to_string(),
+            })?;
+        super::setup_directories(&self.context.service, &ace.env)
+            .await
+            .map_err(|err| Error::Io {
+                message: err.to_string(),
+            })?;
 
         let countdown = airupfx::time::countdown(self.context.service.exec.start_timeout());
 
         if let Some(x) = &self.context.service.exec.pre_start {
+            self.context
+                .record_timing(|timings, now| timings.pre_start_began = Some(now));
             for line in x.lines() {
                 ace.run_wait_timeout(line.trim(), countdown.left())
                     .await??;
             }
+            self.context
+                .record_timing(|timings, now| timings.pre_start_finished = Some(now));
         }
 
         match self.context.service.service.kind {
@@ -106,6 +152,8 @@ impl StartService {
         }
 
         self.context.status.set(Status::Active);
+        self.context
+            .record_timing(|timings, now| timings.active = Some(now));
 
         Ok(())
     }
@@ -1,7 +1,11 @@
 use super::*;
+use crate::{
+    ace::{Child, KILL_SIGNAL_TIMEOUT},
+    supervisor::KillTarget,
+};
 use airup_sdk::system::Status;
-use airupfx::signal::SIGTERM;
-use std::sync::Arc;
+use airupfx::{isolator::Realm, time::Countdown};
+use std::{sync::Arc, time::Duration};
 
 #[derive(Debug)]
 pub struct StopServiceHandle {
@@ -72,7 +76,7 @@ impl StopService {
             }
             None => {
                 if let Some(x) = self.context.child.read().await.as_ref() {
-                    x.kill_timeout(SIGTERM, countdown.left()).await?;
+                    stop_processes(&self.context, x, &countdown).await?;
                 } else {
                     return Err(Error::unsupported("this service cannot be stopped"));
                 }
@@ -82,7 +86,133 @@ impl StopService {
         self.context.status.set(Status::Stopped);
 
         _ = super::cleanup::cleanup_service(&ace, &self.context.service, &countdown).await;
+        self.context.release_dynamic_user();
 
         Ok(())
     }
 }
+
+#[derive(Debug)]
+pub struct KillServiceHandle {
+    helper: TaskHelperHandle,
+}
+impl TaskHandle for KillServiceHandle {
+    fn task_class(&self) -> &'static str {
+        "KillService"
+    }
+
+    fn is_important(&self) -> bool {
+        true
+    }
+
+    fn send_interrupt(&self) {
+        self.helper.send_interrupt()
+    }
+
+    fn wait(&self) -> BoxFuture<'_, Result<TaskFeedback, Error>> {
+        self.helper.wait()
+    }
+}
+
+/// Starts a task which forces the service to stop. Unlike `StopService`, this is not registered as the current task of the
+/// supervisor, so it can run while another task, e.g. `StopService`, is running.
+pub(in crate::supervisor) fn kill(context: Arc<SupervisorContext>) -> Arc<dyn TaskHandle> {
+    let (handle, helper) = task_helper();
+
+    tokio::spawn(async move {
+        let val = helper.would_interrupt(kill_processes(&context)).await;
+        helper.finish(val.flatten());
+    });
+
+    Arc::new(KillServiceHandle { helper: handle })
+}
+
+/// Sends the kill signal to processes of the service selected by its kill mode, escalating as [`force_stop`] does. The whole
+/// realm is killed instead of sending `SIGKILL`, regardless of the kill mode.
+async fn kill_processes(context: &SupervisorContext) -> Result<(), Error> {
+    let child = context.child.read().await;
+    let Some(child) = &*child else {
+        return Ok(());
+    };
+    let pid = child.id();
+    let target = KillTarget::select(
+        context.realm.as_deref(),
+        pid,
+        context.service.exec.kill_mode,
+    );
+    child.abort();
+
+    // The main process is waited by the supervisor, rather than this task, so its existence is polled instead.
+    let exited = async |timeout| target.wait_exited(pid, Some(timeout)).await;
+    force_stop(context, child, &target, context.realm.as_deref(), exited).await
+}
+
+/// Sends the stop signal to processes of the service selected by its kill mode, then waits for them to exit. If they did not
+/// exit in the grace period, they are forced to stop by [`force_stop`].
+async fn stop_processes(
+    context: &SupervisorContext,
+    child: &Child,
+    countdown: &Countdown,
+) -> Result<(), Error> {
+    let exec = &context.service.exec;
+    let target = KillTarget::select(context.realm.as_deref(), child.id(), exec.kill_mode);
+    child.abort();
+    target.send(child, exec.stop_signal()).await?;
+
+    let grace_period = match (exec.stop_grace_period(), countdown.left()) {
+        (Some(x), Some(y)) => Some(x.min(y)),
+        (x, y) => x.or(y),
+    };
+    if wait_exited(child, &target, grace_period).await? {
+        return Ok(());
+    }
+
+    let exited = async |timeout| wait_exited(child, &target, Some(timeout)).await;
+    force_stop(context, child, &target, None, exited).await
+}
+
+/// Sends the kill signal of the service to the target. If the kill signal is not `SIGKILL`, and `exited` reports that the
+/// processes did not exit in [`KILL_SIGNAL_TIMEOUT`], `SIGKILL` is sent to them, or the whole realm is killed if `realm` is
+/// given.
+async fn force_stop<F>(
+    context: &SupervisorContext,
+    child: &Child,
+    target: &KillTarget,
+    realm: Option<&Realm>,
+    exited: impl FnOnce(Duration) -> F,
+) -> Result<(), Error>
+where
+    F: Future<Output = Result<bool, Error>>,
+{
+    let kill_signal = context.service.exec.kill_signal();
+    if kill_signal != libc::SIGKILL {
+        target.send(child, kill_signal).await?;
+        if exited(KILL_SIGNAL_TIMEOUT).await? {
+            return Ok(());
+        }
+    }
+
+    if let Some(realm) = realm
+        && realm.kill().is_ok()
+    {
+        return Ok(());
+    }
+    target.send(child, libc::SIGKILL).await
+}
+
+/// Waits until all processes of the target exited. Returns `false` if the timeout expired.
+async fn wait_exited(
+    child: &Child,
+    target: &KillTarget,
+    timeout: Option<Duration>,
+) -> Result<bool, Error> {
+    let countdown = airupfx::time::countdown(timeout);
+    match countdown.left() {
+        Some(x) if x.is_zero() => Ok(false),
+        left => match child.wait_timeout(left).await {
+            Ok(_) => Ok(target.wait_timeout(countdown.left()).await),
+            Err(crate::ace::Error::TimedOut) => Ok(false),
+            Err(err) => Err(err.into()),
+        },
+    }
+}
@@ -0,0 +1,121 @@
+//! Parser of `dotenv`-formatted environment files.
+//!
+//! Each non-empty line of the file is either a comment starting with `#`, or an assignment in the form `KEY=VALUE`, which may
+//! be prefixed with `export `. Values can be unquoted, single-quoted (taken literally) or double-quoted (supporting `\n`, `\t`,
+//! `\\`, `\"` and `\$` escapes).
+
+use std::path::Path;
+
+/// Reads and parses the specified `dotenv` file.
+///
+/// # Errors
+/// An `Err(_)` is returned if the file could not be read or it is malformed.
+pub async fn read(path: &Path) -> std::io::Result<Vec<(String, String)>> {
+    let content = tokio::fs::read_to_string(path).await?;
+    parse(&content).map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))
+}
+
+/// Parses a `dotenv`-formatted string into a list of key-value pairs, in order of appearance.
+pub fn parse(s: &str) -> Result<Vec<(String, String)>, String> {
+    let mut result = Vec::new();
+    for (index, line) in s.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let error = |msg: &str| format!("line {}: {msg}", index + 1);
+
+        let (key, value) = line.split_once('=').ok_or_else(|| error("expected `=`"))?;
+        let key = key.trim();
+        let key_valid = !key.is_empty()
+            && key
+                .chars()
+                .all(|x| x.is_ascii_alphanumeric() || x == '_' || x == '.');
+        if !key_valid {
+            return Err(error("invalid variable name"));
+        }
+
+        result.push((key.into(), parse_value(value.trim()).map_err(error)?));
+    }
+
+    Ok(result)
+}
+
+/// Parses value part of an assignment.
+fn parse_value(s: &str) -> Result<String, &'static str> {
+    let (value, rest) = if let Some(s) = s.strip_prefix('\'') {
+        let (value, rest) = s.split_once('\'').ok_or("unterminated single quote")?;
+        (value.into(), rest)
+    } else if let Some(s) = s.strip_prefix('"') {
+        let mut value = String::with_capacity(s.len());
+        let mut chars = s.char_indices();
+        let end = loop {
+            match chars.next().ok_or("unterminated double quote")? {
+                (i, '"') => break i,
+                (_, '\\') => match chars.next().ok_or("unterminated double quote")?.1 {
+                    'n' => value.push('\n'),
+                    't' => value.push('\t'),
+                    x @ ('\\' | '"' | '$') => value.push(x),
+                    x => {
+                        value.push('\\');
+                        value.push(x);
+                    }
+                },
+                (_, x) => value.push(x),
+            }
+        };
+        (value, &s[end + 1..])
+    } else {
+        // An unquoted value ends at a comment, which must be preceded by whitespace.
+        let end = s
+            .char_indices()
+            .find(|&(i, x)| x == '#' && s[..i].ends_with(char::is_whitespace))
+            .map(|x| x.0)
+            .unwrap_or(s.len());
+        return Ok(s[..end].trim_end().into());
+    };
+
+    let rest = rest.trim_start();
+    if !rest.is_empty() && !rest.starts_with('#') {
+        return Err("unexpected characters after quoted value");
+    }
+
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn parse() {
+        let parsed = super::parse(
+            r#"
+            # comment
+            A=1
+            export B = two words # comment
+            C='lit$eral # not comment'
+            D="line\nbreak \"quoted\"" # comment
+            E=
+            F=a#b
+            "#,
+        )
+        .unwrap();
+        let expected = [
+            ("A", "1"),
+            ("B", "two words"),
+            ("C", "lit$eral # not comment"),
+            ("D", "line\nbreak \"quoted\""),
+            ("E", ""),
+            ("F", "a#b"),
+        ];
+        assert_eq!(parsed.len(), expected.len());
+        for ((k, v), (ek, ev)) in parsed.iter().zip(expected) {
+            assert_eq!((k.as_str(), v.as_str()), (ek, ev));
+        }
+
+        assert!(super::parse("NO_EQUALS").is_err());
+        assert!(super::parse("BAD KEY=1").is_err());
+        assert!(super::parse("A=\"unterminated").is_err());
+        assert!(super::parse("A='x' trailing").is_err());
+    }
+}
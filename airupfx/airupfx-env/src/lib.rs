@@ -1,8 +1,13 @@
 //! Inspection and manipulation of the process's environment.
 
+pub mod dotenv;
 pub mod users;
+pub mod virt;
 
-pub use users::{current_uid, with_current_user, with_user_by_id, with_user_by_name};
+pub use users::{
+    current_uid, is_id_used, with_current_user, with_group_by_id, with_group_by_name,
+    with_user_by_id, with_user_by_name,
+};
 
 use std::{
     ffi::{OsStr, OsString},
@@ -1,16 +1,22 @@
 //! Inspection and manipulation of the operating system's multi-user function.
 
 use std::sync::{OnceLock, RwLock};
-use sysinfo::{Uid, User};
+use sysinfo::{Gid, Group, Uid, User};
 
 fn sysinfo_users() -> &'static RwLock<sysinfo::Users> {
     static USERS: OnceLock<RwLock<sysinfo::Users>> = OnceLock::new();
     USERS.get_or_init(|| sysinfo::Users::new_with_refreshed_list().into())
 }
 
+fn sysinfo_groups() -> &'static RwLock<sysinfo::Groups> {
+    static GROUPS: OnceLock<RwLock<sysinfo::Groups>> = OnceLock::new();
+    GROUPS.get_or_init(|| sysinfo::Groups::new_with_refreshed_list().into())
+}
+
 /// Refreshes users database.
 pub fn refresh() {
     sysinfo_users().write().unwrap().refresh();
+    sysinfo_groups().write().unwrap().refresh();
 }
 
 /// Finds a user entry by UID.
@@ -31,6 +37,32 @@ pub fn with_user_by_name<F: FnOnce(&User) -> T, T>(name: &str, f: F) -> Option<T
         .find(|u| u.name() == name)?))
 }
 
+/// Finds a group entry by GID.
+pub fn with_group_by_id<F: FnOnce(&Group) -> T, T>(gid: &Gid, f: F) -> Option<T> {
+    Some(f(sysinfo_groups()
+        .read()
+        .unwrap()
+        .iter()
+        .find(|g| g.id() == gid)?))
+}
+
+/// Finds a group entry by group name.
+pub fn with_group_by_name<F: FnOnce(&Group) -> T, T>(name: &str, f: F) -> Option<T> {
+    Some(f(sysinfo_groups()
+        .read()
+        .unwrap()
+        .iter()
+        .find(|g| g.name() == name)?))
+}
+
+/// Returns `true` if the given numeric ID is used by either a user or a group.
+pub fn is_id_used(id: u32) -> bool {
+    let uid = Uid::try_from(id as usize).ok();
+    let gid = Gid::try_from(id as usize).ok();
+    uid.is_some_and(|x| with_user_by_id(&x, |_| ()).is_some())
+        || gid.is_some_and(|x| with_group_by_id(&x, |_| ()).is_some())
+}
+
 /// Returns the user entry of current user.
 pub fn with_current_user<F: FnOnce(&User) -> T, T>(f: F) -> Option<T> {
     with_user_by_id(&current_uid(), f)
@@ -0,0 +1,121 @@
+//! Detection of the virtualization environment that the system is running in.
+
+use std::{path::Path, sync::OnceLock};
+
+/// Kind of virtualization.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Virtualization {
+    /// The system is running on bare metal.
+    None,
+
+    /// The system is running in a container, e.g. Docker, Podman or LXC.
+    Container,
+
+    /// The system is running in a virtual machine.
+    Vm,
+}
+
+/// Returns the virtualization environment that the system is running in.
+///
+/// The result is detected on the first call and cached for the rest of the process's lifetime. If the system runs in a
+/// container inside a virtual machine, [`Virtualization::Container`] is returned.
+pub fn detect() -> Virtualization {
+    static VIRTUALIZATION: OnceLock<Virtualization> = OnceLock::new();
+
+    *VIRTUALIZATION.get_or_init(|| {
+        if is_container() {
+            Virtualization::Container
+        } else if is_vm() {
+            Virtualization::Vm
+        } else {
+            Virtualization::None
+        }
+    })
+}
+
+fn is_container() -> bool {
+    let markers = [
+        "/.dockerenv",
+        "/run/.containerenv",
+        "/run/systemd/container",
+    ];
+    if markers.iter().any(|x| Path::new(x).exists()) {
+        return true;
+    }
+
+    // Most container managers set `container=` in the environment of the init process.
+    std::fs::read("/proc/1/environ").is_ok_and(|x| {
+        x.split(|x| *x == b'\0')
+            .any(|x| x.starts_with(b"container="))
+    })
+}
+
+fn is_vm() -> bool {
+    if Path::new("/proc/xen").exists() || Path::new("/sys/hypervisor/type").exists() {
+        return true;
+    }
+
+    let read = |x| std::fs::read_to_string(x).unwrap_or_default();
+    if is_vm_dmi(
+        &read("/sys/class/dmi/id/sys_vendor"),
+        &read("/sys/class/dmi/id/product_name"),
+    ) {
+        return true;
+    }
+
+    // The `hypervisor` CPU flag is set by most hypervisors on x86.
+    std::fs::read_to_string("/proc/cpuinfo").is_ok_and(|x| {
+        x.lines()
+            .filter(|x| x.starts_with("flags"))
+            .any(|x| x.split_ascii_whitespace().any(|x| x == "hypervisor"))
+    })
+}
+
+/// Returns `true` if the DMI system vendor or product name is known to be reported by a virtual machine.
+///
+/// Vendors that also make physical hardware, e.g. Microsoft and Google, are only matched by their virtual machine products.
+fn is_vm_dmi(sys_vendor: &str, product_name: &str) -> bool {
+    const SYS_VENDORS: &[&str] = &[
+        "QEMU",
+        "VMware",
+        "innotek",
+        "Xen",
+        "Parallels",
+        "Bochs",
+        "Amazon EC2",
+    ];
+    const PRODUCT_NAMES: &[&str] = &[
+        "KVM",
+        "VMware",
+        "VirtualBox",
+        "Virtual Machine",
+        "Google Compute Engine",
+    ];
+
+    SYS_VENDORS.iter().any(|x| sys_vendor.trim().starts_with(x))
+        || PRODUCT_NAMES
+            .iter()
+            .any(|x| product_name.trim().starts_with(x))
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn is_vm_dmi() {
+        assert!(super::is_vm_dmi(
+            "QEMU\n",
+            "Standard PC (Q35 + ICH9, 2009)\n"
+        ));
+        assert!(super::is_vm_dmi(
+            "Microsoft Corporation\n",
+            "Virtual Machine\n"
+        ));
+        assert!(super::is_vm_dmi("Google\n", "Google Compute Engine\n"));
+        assert!(!super::is_vm_dmi(
+            "Microsoft Corporation\n",
+            "Surface Laptop 5\n"
+        ));
+        assert!(!super::is_vm_dmi("Google\n", "Pixelbook\n"));
+        assert!(!super::is_vm_dmi("", ""));
+    }
+}
@@ -2,6 +2,8 @@
 //!
 //! This is useful for compatibility with operating systems that support no isolators.
 
+use crate::CpuUsage;
+
 #[derive(Debug)]
 pub struct Realm;
 impl Realm {
@@ -26,6 +28,18 @@ impl Realm {
     }
 
     pub fn memory_usage(&self) -> std::io::Result<usize> {
-        Ok(0)
+        Err(std::io::ErrorKind::Unsupported.into())
+    }
+
+    pub fn memory_peak(&self) -> std::io::Result<usize> {
+        Err(std::io::ErrorKind::Unsupported.into())
+    }
+
+    pub fn cpu_usage(&self) -> std::io::Result<CpuUsage> {
+        Err(std::io::ErrorKind::Unsupported.into())
+    }
+
+    pub fn pids(&self) -> std::io::Result<Vec<i64>> {
+        Err(std::io::ErrorKind::Unsupported.into())
     }
 }
@@ -43,4 +43,29 @@ impl Realm {
     pub fn memory_usage(&self) -> std::io::Result<usize> {
         self.0.memory_usage()
     }
+
+    /// Returns the maximum bytes of memory that were ever used in the realm.
+    pub fn memory_peak(&self) -> std::io::Result<usize> {
+        self.0.memory_peak()
+    }
+
+    /// Returns how much CPU time was consumed by processes in the realm.
+    pub fn cpu_usage(&self) -> std::io::Result<CpuUsage> {
+        self.0.cpu_usage()
+    }
+
+    /// Returns a list of IDs of processes in the realm.
+    pub fn pids(&self) -> std::io::Result<Vec<i64>> {
+        self.0.pids()
+    }
+}
+
+/// CPU time consumed by processes in a realm.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CpuUsage {
+    /// CPU time spent in user mode, in microseconds.
+    pub user: u64,
+
+    /// CPU time spent in kernel mode, in microseconds.
+    pub system: u64,
 }
@@ -1,3 +1,4 @@
+use crate::CpuUsage;
 use cgroups_rs::{
     CgroupPid, cgroup_builder::CgroupBuilder, cpu::CpuController, memory::MemController,
 };
@@ -95,6 +96,57 @@ impl Realm {
             .usage_in_bytes as usize)
     }
 
+    pub fn memory_peak(&self) -> std::io::Result<usize> {
+        let peak = self
+            .cg
+            .controller_of::<MemController>()
+            .ok_or_else(|| std::io::Error::from(ErrorKind::PermissionDenied))?
+            .memory_stat()
+            .max_usage_in_bytes;
+
+        match peak {
+            0 => Err(ErrorKind::Unsupported.into()),
+            x => Ok(x as usize),
+        }
+    }
+
+    pub fn cpu_usage(&self) -> std::io::Result<CpuUsage> {
+        let stat = self
+            .cg
+            .controller_of::<CpuController>()
+            .ok_or_else(|| std::io::Error::from(ErrorKind::PermissionDenied))?
+            .cpu()
+            .stat;
+
+        // Only the unified hierarchy reports `user_usec` and `system_usec` in `cpu.stat`. On legacy hierarchies, this is
+        // reported by the `cpuacct` controller, which we don't mount, so it is considered unsupported.
+        let mut usage = CpuUsage::default();
+        let mut found = false;
+        for line in stat.lines() {
+            let Some((key, value)) = line.split_once(' ') else {
+                continue;
+            };
+            let Ok(value) = value.trim().parse() else {
+                continue;
+            };
+            match key {
+                "user_usec" => usage.user = value,
+                "system_usec" => usage.system = value,
+                _ => continue,
+            }
+            found = true;
+        }
+
+        match found {
+            true => Ok(usage),
+            false => Err(ErrorKind::Unsupported.into()),
+        }
+    }
+
+    pub fn pids(&self) -> std::io::Result<Vec<i64>> {
+        Ok(self.cg.procs().into_iter().map(|x| x.pid as i64).collect())
+    }
+
     fn pid_detect() -> std::io::Result<()> {
         match std::process::id() {
             1 => Ok(()),
@@ -0,0 +1,13 @@
+//! A fallback process inspector implementation that fails for all operations.
+//!
+//! This is useful for compatibility with operating systems that we do not know how to inspect processes on.
+
+use super::ProcessInfo;
+
+pub fn processes() -> std::io::Result<Vec<ProcessInfo>> {
+    Err(std::io::ErrorKind::Unsupported.into())
+}
+
+pub fn process(_: i64) -> std::io::Result<ProcessInfo> {
+    Err(std::io::ErrorKind::Unsupported.into())
+}
@@ -9,8 +9,18 @@ cfg_if::cfg_if! {
     }
 }
 
+cfg_if::cfg_if! {
+    if #[cfg(target_os = "linux")] {
+        #[path = "linux.rs"]
+        mod inspect;
+    } else {
+        #[path = "fallback.rs"]
+        mod inspect;
+    }
+}
+
 use airupfx_io::line_piper::Callback as LinePiperCallback;
-use std::{convert::Infallible, ffi::OsString, path::PathBuf};
+use std::{convert::Infallible, ffi::OsString, os::fd::OwnedFd, path::PathBuf, sync::Arc};
 
 /// Returns `true` if supervising `forking` services are supported on the system.
 pub fn is_forking_supervisable() -> bool {
@@ -34,6 +44,103 @@ pub fn reload_image() -> std::io::Result<Infallible> {
     sys::reload_image()
 }
 
+/// Sends a signal to the specified process.
+///
+/// # Errors
+/// An `Err(_)` is returned if the process does not exist, or we are not permitted to send signals to it.
+pub fn kill(pid: i64, signum: i32) -> std::io::Result<()> {
+    sys::kill(pid as _, signum)
+}
+
+/// Returns information about all processes running on the system.
+///
+/// # Errors
+/// An `Err(_)` is returned if the underlying OS function failed, or inspecting processes is unsupported on the system.
+pub fn processes() -> std::io::Result<Vec<ProcessInfo>> {
+    inspect::processes()
+}
+
+/// Returns information about the specified process.
+///
+/// # Errors
+/// An `Err(_)` is returned if the process does not exist, or inspecting processes is unsupported on the system.
+pub fn process(pid: i64) -> std::io::Result<ProcessInfo> {
+    inspect::process(pid)
+}
+
+/// Returns information about all processes in the process tree of `pid`, including itself.
+///
+/// A process is considered in the tree if it is a descendant of `pid`, or it belongs to the session led by `pid`. The latter
+/// rule makes daemons that double-forked and got reparented still counted.
+///
+/// # Errors
+/// An `Err(_)` is returned if the underlying OS function failed, or inspecting processes is unsupported on the system.
+pub fn tree(pid: i64) -> std::io::Result<Vec<ProcessInfo>> {
+    let all = processes()?;
+    let mut result: Vec<ProcessInfo> = Vec::with_capacity(8);
+    let mut pending = vec![pid];
+    while let Some(parent) = pending.pop() {
+        for info in &all {
+            let related = info.pid == parent || info.ppid == parent || info.sid == pid;
+            if related && !result.iter().any(|x| x.pid == info.pid) {
+                pending.push(info.pid);
+                result.push(info.clone());
+            }
+        }
+    }
+
+    Ok(result)
+}
+
+/// Information about a process running on the system.
+#[derive(Debug, Clone, Default)]
+pub struct ProcessInfo {
+    /// ID of the process.
+    pub pid: i64,
+
+    /// ID of the process' parent.
+    pub ppid: i64,
+
+    /// ID of the process group the process belongs to.
+    pub pgid: i64,
+
+    /// ID of the session the process belongs to.
+    pub sid: i64,
+
+    /// Real UID of the process.
+    pub uid: u32,
+
+    /// Name of the process' executable.
+    pub name: String,
+
+    /// Command-line arguments of the process. This is empty for kernel threads and zombies.
+    pub cmdline: Vec<String>,
+
+    /// Timestamp generated when the process was started, in milliseconds.
+    pub start_time: i64,
+
+    /// CPU time spent in user mode by the process and its waited-for children, in microseconds.
+    pub cpu_user: u64,
+
+    /// CPU time spent in kernel mode by the process and its waited-for children, in microseconds.
+    pub cpu_system: u64,
+
+    /// Bytes read from the storage layer by the process.
+    pub io_read: u64,
+
+    /// Bytes written to the storage layer by the process.
+    pub io_write: u64,
+
+    /// Number of threads in the process.
+    pub threads: u64,
+
+    /// Resident memory size of the process, in bytes.
+    pub memory: u64,
+
+    /// Peak resident memory size of the process, in bytes.
+    pub memory_peak: u64,
+}
+
 /// Describes the result of calling `wait`-series methods.
 #[derive(Debug, Clone)]
 pub struct Wait {
@@ -159,6 +266,9 @@ pub enum Stdio {
 
     /// The child's stdio is redirected to the file.
     File(PathBuf),
+
+    /// The child's stdio is redirected to a duplicate of the file descriptor.
+    Fd(Arc<OwnedFd>),
 }
 impl Clone for Stdio {
     fn clone(&self) -> Self {
@@ -167,6 +277,7 @@ impl Clone for Stdio {
             Self::Inherit => Self::Inherit,
             Self::Callback(c) => Self::Callback(c.clone_boxed()),
             Self::File(f) => Self::File(f.clone()),
+            Self::Fd(fd) => Self::Fd(fd.clone()),
         }
     }
 }
@@ -184,6 +295,7 @@ impl Stdio {
                 .into_std()
                 .await
                 .into(),
+            Self::Fd(fd) => fd.try_clone()?.into(),
         })
     }
 }
@@ -0,0 +1,212 @@
+//! Process inspection on Linux, which is implemented by reading `procfs`.
+
+use super::ProcessInfo;
+use std::{path::Path, sync::OnceLock};
+
+/// Returns information about all processes running on the system.
+pub fn processes() -> std::io::Result<Vec<ProcessInfo>> {
+    let mut result = Vec::with_capacity(256);
+    for entry in std::fs::read_dir("/proc")? {
+        let Ok(entry) = entry else {
+            continue;
+        };
+        let Some(pid) = entry.file_name().to_str().and_then(|x| x.parse().ok()) else {
+            continue;
+        };
+
+        // The process may exit while we are reading it, in which case it is simply skipped.
+        if let Ok(info) = process(pid) {
+            result.push(info);
+        }
+    }
+
+    Ok(result)
+}
+
+/// Returns information about the specified process.
+pub fn process(pid: i64) -> std::io::Result<ProcessInfo> {
+    let base = Path::new("/proc").join(pid.to_string());
+    let mut info = parse_stat(pid, &std::fs::read_to_string(base.join("stat"))?)?;
+
+    if let Ok(status) = std::fs::read_to_string(base.join("status")) {
+        parse_status(&mut info, &status);
+    }
+
+    // Reading `/proc/<pid>/io` requires the same privileges as `ptrace`, so this silently fails if we are not permitted.
+    if let Ok(io) = std::fs::read_to_string(base.join("io")) {
+        parse_io(&mut info, &io);
+    }
+
+    if let Ok(cmdline) = std::fs::read(base.join("cmdline")) {
+        info.cmdline = cmdline
+            .split(|x| *x == b'\0')
+            .filter(|x| !x.is_empty())
+            .map(|x| String::from_utf8_lossy(x).into())
+            .collect();
+    }
+
+    Ok(info)
+}
+
+/// Parses content of `/proc/<pid>/stat`.
+fn parse_stat(pid: i64, s: &str) -> std::io::Result<ProcessInfo> {
+    let invalid = || std::io::Error::new(std::io::ErrorKind::InvalidData, "malformed `stat` file");
+
+    // The second field is the executable name in parentheses, which may contain spaces or parentheses itself, so we split
+    // the string at the last `)`.
+    let (head, tail) = s.rsplit_once(')').ok_or_else(invalid)?;
+    let name = head.split_once('(').ok_or_else(invalid)?.1;
+    let fields: Vec<&str> = tail.split_ascii_whitespace().collect();
+    let field = |n: usize| -> std::io::Result<u64> {
+        // Field numbers are the ones documented in `proc(5)`, which are 1-based and count the PID and the name.
+        fields
+            .get(n - 3)
+            .and_then(|x| x.parse().ok())
+            .ok_or_else(invalid)
+    };
+
+    let ticks_to_us = |ticks: u64| ticks * 1_000_000 / clock_ticks();
+    Ok(ProcessInfo {
+        pid,
+        ppid: field(4)? as _,
+        pgid: field(5)? as _,
+        sid: field(6)? as _,
+        uid: 0,
+        name: name.into(),
+        cmdline: Vec::new(),
+        start_time: boot_time() + (field(22)? * 1000 / clock_ticks()) as i64,
+        cpu_user: ticks_to_us(field(14)? + field(16)?),
+        cpu_system: ticks_to_us(field(15)? + field(17)?),
+        io_read: 0,
+        io_write: 0,
+        threads: field(20)?,
+        memory: field(24)? * page_size(),
+        memory_peak: 0,
+    })
+}
+
+/// Parses content of `/proc/<pid>/status`, filling fields that are not available in `/proc/<pid>/stat`.
+fn parse_status(info: &mut ProcessInfo, s: &str) {
+    for line in s.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let mut value = value.split_ascii_whitespace();
+        match key {
+            "Uid" => {
+                if let Some(x) = value.next().and_then(|x| x.parse().ok()) {
+                    info.uid = x;
+                }
+            }
+            "VmHWM" => {
+                if let Some(x) = value.next().and_then(|x| x.parse::<u64>().ok()) {
+                    info.memory_peak = x * 1024;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Parses content of `/proc/<pid>/io`.
+fn parse_io(info: &mut ProcessInfo, s: &str) {
+    for line in s.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let Ok(value) = value.trim().parse() else {
+            continue;
+        };
+        match key {
+            "read_bytes" => info.io_read = value,
+            "write_bytes" => info.io_write = value,
+            _ => {}
+        }
+    }
+}
+
+/// Returns the system's boot time, in milliseconds since the Unix epoch.
+fn boot_time() -> i64 {
+    static BOOT_TIME: OnceLock<i64> = OnceLock::new();
+
+    *BOOT_TIME.get_or_init(|| {
+        std::fs::read_to_string("/proc/stat")
+            .ok()
+            .and_then(|s| {
+                s.lines()
+                    .find_map(|x| x.strip_prefix("btime "))
+                    .and_then(|x| x.trim().parse::<i64>().ok())
+            })
+            .map(|x| x * 1000)
+            .unwrap_or_default()
+    })
+}
+
+/// Returns number of clock ticks per second.
+fn clock_ticks() -> u64 {
+    static CLOCK_TICKS: OnceLock<u64> = OnceLock::new();
+
+    *CLOCK_TICKS.get_or_init(|| match unsafe { libc::sysconf(libc::_SC_CLK_TCK) } {
+        x if x > 0 => x as u64,
+        _ => 100,
+    })
+}
+
+/// Returns size of a memory page, in bytes.
+fn page_size() -> u64 {
+    static PAGE_SIZE: OnceLock<u64> = OnceLock::new();
+
+    *PAGE_SIZE.get_or_init(|| match unsafe { libc::sysconf(libc::_SC_PAGESIZE) } {
+        x if x > 0 => x as u64,
+        _ => 4096,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn parse_stat() {
+        let stat = "1234 (my (weird) daemon) S 1 1234 1234 0 -1 4194560 1000 0 0 0 200 100 30 20 20 0 3 0 \
+                    500 10000000 256 18446744073709551615 1 1 0 0 0 0 0 4096 0 0 0 0 17 0 0 0 0 0 0";
+        let info = super::parse_stat(1234, stat).unwrap();
+        let ticks = super::clock_ticks();
+        assert_eq!(info.name, "my (weird) daemon");
+        assert_eq!(info.ppid, 1);
+        assert_eq!(info.pgid, 1234);
+        assert_eq!(info.sid, 1234);
+        assert_eq!(info.threads, 3);
+        assert_eq!(info.cpu_user, 230 * 1_000_000 / ticks);
+        assert_eq!(info.cpu_system, 120 * 1_000_000 / ticks);
+        assert_eq!(info.memory, 256 * super::page_size());
+
+        super::parse_stat(1, "1 (init").unwrap_err();
+    }
+
+    #[test]
+    fn parse_status_and_io() {
+        let mut info =
+            super::parse_stat(1, "1 (init) S 0 1 1 0 -1 0 0 0 0 0 0 0 0 0 20 0 1 0 1 0 0").unwrap();
+        super::parse_status(
+            &mut info,
+            "Name:\tinit\nUid:\t1000\t1000\t1000\t1000\nVmHWM:\t  2048 kB\n",
+        );
+        super::parse_io(&mut info, "rchar: 1\nread_bytes: 4096\nwrite_bytes: 8192\n");
+        assert_eq!(info.uid, 1000);
+        assert_eq!(info.memory_peak, 2048 * 1024);
+        assert_eq!(info.io_read, 4096);
+        assert_eq!(info.io_write, 8192);
+    }
+
+    #[test]
+    fn current_process() {
+        let info = super::process(std::process::id() as _).unwrap();
+        assert_eq!(info.pid, std::process::id() as i64);
+        assert!(!info.cmdline.is_empty());
+        assert!(
+            super::processes()
+                .unwrap()
+                .iter()
+                .any(|x| x.pid == info.pid)
+        );
+    }
+}
@@ -141,7 +141,7 @@ pub fn reload_image() -> std::io::Result<Infallible> {
 ///
 /// # Errors
 /// An `Err(_)` is returned if the underlying OS function failed.
-fn kill(pid: Pid, signum: i32) -> std::io::Result<()> {
+pub(crate) fn kill(pid: Pid, signum: i32) -> std::io::Result<()> {
     let result = unsafe { libc::kill(pid as _, signum) };
     match result {
         0 => Ok(()),